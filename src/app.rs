@@ -4,15 +4,16 @@ use std::sync::{Arc, RwLock};
 
 use crate::book_handler::BookHandler;
 use crate::config::Config;
+use crate::document::{self, DocumentError};
 use crate::draw::{self, Canvas};
-use crate::epub;
+use crate::epub::{self, PageProgression};
 use crate::text::fonts::FontIndexer;
-use crate::text::geom::Rect;
+use crate::text::geom::{Point, Rect};
 use crate::text::{self, TypesetConfig};
 use thiserror::Error;
 use winit::application::ApplicationHandler;
-use winit::dpi::{PhysicalSize, Size};
-use winit::event::{ElementState, KeyEvent, WindowEvent};
+use winit::dpi::{PhysicalPosition, PhysicalSize, Size};
+use winit::event::{ElementState, KeyEvent, MouseButton, WindowEvent};
 use winit::event_loop::ActiveEventLoop;
 use winit::keyboard::{Key, NamedKey};
 use winit::window::{Window, WindowId};
@@ -28,6 +29,9 @@ pub enum Error {
     #[error("book error")]
     Book(#[from] epub::EpubError),
 
+    #[error("document error")]
+    Document(#[from] DocumentError),
+
     #[error("malformed image tag")]
     ImageTag,
 
@@ -54,6 +58,9 @@ pub struct App {
     config: Config,
     typeset_config: Arc<RwLock<TypesetConfig>>,
     book: BookHandler,
+    cursor: Option<PhysicalPosition<f64>>,
+    /// the label typed so far of a print page to go to, while one is being typed
+    page_input: Option<String>,
 }
 
 impl App {
@@ -71,7 +78,17 @@ impl App {
             vertical_margin: config.vertical_margin,
         };
         let tsconfig = Arc::new(RwLock::new(tsconf));
-        let book = BookHandler::new(&path, tsconfig.clone())?;
+        let document = document::open(&path, config.limits, &config.rendition)?;
+        let mut book = BookHandler::new(document, tsconfig.clone())?;
+        if config.embedded_fonts {
+            if let Some(family) = book.embedded_family() {
+                tracing::info!("using embedded font {}", family.name);
+                if let Ok(mut conf) = tsconfig.write() {
+                    conf.family = family.with_fallback(&conf.family);
+                }
+                book.repaginate()?;
+            }
+        }
 
         Ok(Self {
             _font_index: indexer,
@@ -80,6 +97,8 @@ impl App {
             config,
             typeset_config: tsconfig,
             book,
+            cursor: None,
+            page_input: None,
         })
     }
 
@@ -113,9 +132,21 @@ impl App {
             tracing::error!("canvas is None?");
             panic!();
         };
+        let title = self.book.chapter_title().or(self.book.title());
+        let title = match (&self.page_input, title, self.book.print_page()) {
+            (Some(input), ..) => Some(format!("Go to page {}", input)),
+            (None, Some(title), Some(page)) => Some(format!("{} (p. {})", title, page)),
+            (None, title, page) => title.or(page).map(str::to_owned),
+        };
+        if let (Some(win), Some(title)) = (self.window.as_ref(), title) {
+            win.set_title(&title);
+        }
         let config = self.typeset_config.read().map_err(|_| Error::RwLock)?;
         canvas.blank()?;
         page.raster(&config.family, canvas)?;
+        if let Some(note) = self.book.note() {
+            note.raster(&config.family, canvas)?;
+        }
         canvas.present()?;
         Ok(())
     }
@@ -155,6 +186,18 @@ impl ApplicationHandler for App {
                     },
                 ..
             } => match key.as_ref() {
+                // page labels can be roman numerals like "xiv", so while one is being typed
+                // letters are part of it rather than shortcuts
+                Key::Character(text)
+                    if self.page_input.is_some() && text.chars().all(char::is_alphanumeric) =>
+                {
+                    if let Some(input) = self.page_input.as_mut() {
+                        input.push_str(text);
+                    }
+                    if let Some(win) = self.window.as_ref() {
+                        win.request_redraw();
+                    }
+                }
                 Key::Character("+") => {
                     if let Some(_win) = self.window.as_ref() {
                         //let mut config = self.glyphs.config();
@@ -186,18 +229,104 @@ impl ApplicationHandler for App {
                 //        win.request_redraw();
                 //    }
                 //}
-                Key::Named(NamedKey::ArrowLeft) => {
-                    if let None = self.book.prev_page().ok() {
-                        tracing::warn!("no previous page");
+                Key::Named(key @ (NamedKey::ArrowLeft | NamedKey::ArrowRight)) => {
+                    // pages turn towards the left in right-to-left books
+                    let rtl = self.book.page_progression() == PageProgression::Rtl;
+                    let forward = (key == NamedKey::ArrowRight) != rtl;
+                    let res = if forward {
+                        self.book.next_page()
+                    } else {
+                        self.book.prev_page()
+                    };
+                    if res.is_err() {
+                        tracing::warn!("no {} page", if forward { "next" } else { "previous" });
+                        return;
+                    }
+                    if let Some(win) = self.window.as_ref() {
+                        win.request_redraw();
+                    }
+                }
+                Key::Named(NamedKey::PageUp) => {
+                    if self.book.prev_chapter().is_err() {
+                        tracing::warn!("no previous chapter");
+                        return;
+                    }
+                    if let Some(win) = self.window.as_ref() {
+                        win.request_redraw();
+                    }
+                }
+                Key::Named(NamedKey::PageDown) => {
+                    if self.book.next_chapter().is_err() {
+                        tracing::warn!("no next chapter");
+                        return;
+                    }
+                    if let Some(win) = self.window.as_ref() {
+                        win.request_redraw();
+                    }
+                }
+                Key::Character("g") => {
+                    self.page_input = Some(String::new());
+                    if let Some(win) = self.window.as_ref() {
+                        win.request_redraw();
+                    }
+                }
+                Key::Character(digit) if digit.chars().all(|c| c.is_ascii_digit()) => {
+                    self.page_input = Some(digit.to_owned());
+                    if let Some(win) = self.window.as_ref() {
+                        win.request_redraw();
+                    }
+                }
+                Key::Named(NamedKey::Enter) if self.page_input.is_some() => {
+                    let label = self.page_input.take().unwrap_or_default();
+                    if !label.is_empty() {
+                        match self.book.goto_print_page(&label) {
+                            Ok(true) => (),
+                            Ok(false) => tracing::warn!("book has no print page {}", label),
+                            Err(e) => {
+                                tracing::warn!("unable to go to print page {}: {}", label, e)
+                            }
+                        }
+                    }
+                    if let Some(win) = self.window.as_ref() {
+                        win.request_redraw();
+                    }
+                }
+                Key::Named(NamedKey::Home) => {
+                    if let Err(e) = self.book.goto_start() {
+                        tracing::warn!("unable to go to the start: {}", e);
                         return;
                     }
                     if let Some(win) = self.window.as_ref() {
                         win.request_redraw();
                     }
                 }
-                Key::Named(NamedKey::ArrowRight) => {
-                    if let None = self.book.next_page().ok() {
-                        tracing::warn!("no next page");
+                Key::Character("t") => {
+                    match self.book.goto_contents() {
+                        Ok(true) => (),
+                        Ok(false) => {
+                            tracing::warn!("book has no contents page");
+                            return;
+                        }
+                        Err(e) => {
+                            tracing::warn!("unable to go to the contents: {}", e);
+                            return;
+                        }
+                    }
+                    if let Some(win) = self.window.as_ref() {
+                        win.request_redraw();
+                    }
+                }
+                Key::Named(NamedKey::Backspace) if self.page_input.is_some() => {
+                    if let Some(input) = self.page_input.as_mut() {
+                        input.pop();
+                    }
+                    if let Some(win) = self.window.as_ref() {
+                        win.request_redraw();
+                    }
+                }
+                Key::Named(NamedKey::Backspace) => {
+                    if self.book.back().is_err() {
+                        tracing::warn!("no previous location");
                         return;
                     }
                     if let Some(win) = self.window.as_ref() {
@@ -205,10 +334,58 @@ impl ApplicationHandler for App {
                     }
                 }
                 Key::Named(NamedKey::Escape) => {
-                    event_loop.exit();
+                    if self.page_input.is_some() {
+                        self.page_input = None;
+                        if let Some(win) = self.window.as_ref() {
+                            win.request_redraw();
+                        }
+                        return;
+                    }
+                    if self.book.note().is_none() {
+                        event_loop.exit();
+                        return;
+                    }
+                    self.book.close_note();
+                    if let Some(win) = self.window.as_ref() {
+                        win.request_redraw();
+                    }
                 }
                 _ => (),
             },
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor = Some(position);
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.cursor = None;
+            }
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } => {
+                if self.book.note().is_some() {
+                    self.book.close_note();
+                    if let Some(win) = self.window.as_ref() {
+                        win.request_redraw();
+                    }
+                    return;
+                }
+                let Some(pos) = self.cursor else {
+                    return;
+                };
+                match self
+                    .book
+                    .follow_link(Point::new(pos.x as f32, pos.y as f32))
+                {
+                    Ok(true) => {
+                        if let Some(win) = self.window.as_ref() {
+                            win.request_redraw();
+                        }
+                    }
+                    Ok(false) => (),
+                    Err(e) => tracing::warn!("unable to follow link: {}", e),
+                }
+            }
             WindowEvent::Resized(new_size) => {
                 if let Err(e) = self.resize(new_size) {
                     tracing::error!("resize: {}", e);
@@ -1,71 +1,367 @@
-use std::path::Path;
+use std::cmp::Ordering;
 use std::sync::{Arc, RwLock};
 
 use crate::app::Error;
-use crate::epub::{Book, Content, IndexElement};
+use crate::document::{Document, DocumentError, Location, Section};
+use crate::draw::Image;
+use crate::epub::PageProgression;
 use crate::page::{paginate, Page};
+use crate::text::fonts::{Family, FontIndexer, IndexedFont};
+use crate::text::geom::Point;
 use crate::text::TypesetConfig;
 
 pub struct BookHandler {
-    book: Book,
+    book: Box<dyn Document>,
     config: Arc<RwLock<TypesetConfig>>,
-    current_chapter: Option<IndexElement>,
+    cover: Option<Image>,
+    current_chapter: Option<Location>,
     current_page: usize,
     pages: Vec<Page>,
+    /// locations left by following links, most recent last. `None` is the cover
+    history: Vec<(Option<Location>, usize)>,
+    /// an open footnote, shown over the current page
+    note: Option<Page>,
 }
 impl BookHandler {
-    pub fn new<P: AsRef<Path>>(
-        path: &P,
+    pub fn new(
+        mut book: Box<dyn Document>,
         config: Arc<RwLock<TypesetConfig>>,
     ) -> Result<Self, Error> {
-        let book = Book::new(path)?;
+        let cover = book.cover().unwrap_or_else(|e| {
+            tracing::warn!("unable to load cover: {}", e);
+            None
+        });
+        tracing::info!(
+            "opened \"{}\" by {}",
+            book.title().unwrap_or_default(),
+            book.authors().join(", ")
+        );
         let mut b = Self {
             book,
             config,
+            cover,
             current_chapter: None,
             current_page: 0,
             pages: vec![],
+            history: vec![],
+            note: None,
         };
 
-        {
+        if let Some(start) = b.book.start().cloned() {
+            b.goto(&start)?;
+        } else if b.cover.is_some() {
+            b.show_cover()?;
+        } else {
             // go to first page
-            let Content { item, node } = b.book.first()?;
+            let Section {
+                location: item,
+                node,
+            } = b.book.first()?;
             let c = b.config.read().map_err(|_| Error::RwLock)?;
+            b.pages = paginate(&node, item.path(), &c, b.book.as_mut())?;
             b.current_page = 0;
             b.current_chapter = Some(item);
-            b.pages = paginate(&node, &c, &mut b.book)?;
         }
 
         Ok(b)
     }
 
     pub fn repaginate(&mut self) -> Result<(), Error> {
+        self.note = None;
         if let Some(chap) = self.current_chapter.as_ref() {
-            let content = self.book.content(chap)?;
+            let content = self.book.section(chap)?;
 
             let c = self.config.read().map_err(|_| Error::RwLock)?;
-            self.pages = paginate(content.node(), &c, &mut self.book)?;
+            self.pages = paginate(content.node(), chap.path(), &c, self.book.as_mut())?;
+        } else {
+            self.show_cover()?;
         }
         Ok(())
     }
 
+    fn show_cover(&mut self) -> Result<(), Error> {
+        let Some(cover) = self.cover.as_ref() else {
+            return Ok(());
+        };
+        let c = self.config.read().map_err(|_| Error::RwLock)?;
+        self.note = None;
+        self.pages = vec![Page::cover(cover, &c)];
+        self.current_chapter = None;
+        self.current_page = 0;
+        Ok(())
+    }
+
+    /// The first spine item, skipping a page that only repeats the cover we already show
+    fn first_content(&mut self) -> Result<Section, DocumentError> {
+        let content = self.book.first()?;
+        if self.cover.is_some() && self.book.cover_page() == Some(content.location.id()) {
+            return self.book.next(content.location.id());
+        }
+        Ok(content)
+    }
+
     pub fn page(&self) -> Option<&Page> {
         self.pages.get(self.current_page)
     }
 
+    /// The open footnote panel, to be drawn over the current page
+    pub fn note(&self) -> Option<&Page> {
+        self.note.as_ref()
+    }
+
+    pub fn close_note(&mut self) {
+        self.note = None;
+    }
+
+    /// The main font family embedded in the book, if it has one
+    pub fn embedded_family(&mut self) -> Option<Family> {
+        let fonts =
+            self.book.fonts().into_iter().filter_map(
+                |(path, bytes)| match IndexedFont::from_bytes(bytes, &path) {
+                    Ok(font) => Some(font),
+                    Err(e) => {
+                        tracing::warn!("unable to load font {}: {}", path, e);
+                        None
+                    }
+                },
+            );
+        FontIndexer::from_fonts(fonts).main_family()
+    }
+
+    pub fn page_progression(&self) -> PageProgression {
+        self.book.page_progression()
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.book.title()
+    }
+
+    /// The label of the last toc entry at or before the current page
+    pub fn chapter_title(&self) -> Option<&str> {
+        self.book
+            .toc()
+            .iter()
+            .filter(|e| {
+                e.location()
+                    .is_some_and(|el| self.compare_location(el) != Ordering::Greater)
+            })
+            .last()
+            .map(|e| e.label())
+    }
+
+    /// Open a spine item at the page containing its fragment, or at its first page
+    pub fn goto(&mut self, elem: &Location) -> Result<(), Error> {
+        let Section {
+            location: item,
+            node,
+        } = self.book.section(elem)?;
+        let c = self.config.read().map_err(|_| Error::RwLock)?;
+        self.pages = paginate(&node, item.path(), &c, self.book.as_mut())?;
+        self.note = None;
+        self.current_page = 0;
+        if let Some(fragment) = item.fragment() {
+            if node.find_by_id(fragment).is_none() {
+                tracing::warn!("fragment {} not found in {}", fragment, item.path());
+            }
+            self.current_page = self.anchor_page(fragment).unwrap_or(0);
+        }
+        self.current_chapter = Some(item);
+        Ok(())
+    }
+
+    /// Follow the link under the given point, remembering where we came from. Links to
+    /// notes open them over the current page instead. Returns whether there was a link
+    pub fn follow_link(&mut self, point: Point) -> Result<bool, Error> {
+        let (Some(page), Some(current)) = (self.page(), self.current_chapter.as_ref()) else {
+            return Ok(false);
+        };
+        let Some(link) = page.link_at(point) else {
+            return Ok(false);
+        };
+        let noteref = link.is_noteref();
+        let Some(target) = self.book.resolve_link(current, link.href()) else {
+            tracing::info!("not following link to {}", link.href());
+            return Ok(false);
+        };
+        if let Some(note) = self.note_panel(&target, noteref)? {
+            self.note = Some(note);
+            return Ok(true);
+        }
+        self.history
+            .push((self.current_chapter.clone(), self.current_page));
+        self.goto(&target)?;
+        Ok(true)
+    }
+
+    /// Jump to where the body matter begins, or to the beginning of the book if it doesn't
+    /// say, remembering where we came from
+    pub fn goto_start(&mut self) -> Result<(), Error> {
+        self.history
+            .push((self.current_chapter.clone(), self.current_page));
+        match self.book.start().cloned() {
+            Some(start) => self.goto(&start),
+            None if self.cover.is_some() => self.show_cover(),
+            None => {
+                let item = self.first_content()?.location;
+                self.goto(&item)
+            }
+        }
+    }
+
+    /// Jump to the table of contents page, remembering where we came from. Returns whether
+    /// the book has one
+    pub fn goto_contents(&mut self) -> Result<bool, Error> {
+        let Some(contents) = self.book.contents().cloned() else {
+            return Ok(false);
+        };
+        self.history
+            .push((self.current_chapter.clone(), self.current_page));
+        self.goto(&contents)?;
+        Ok(true)
+    }
+
+    /// Jump to the screen page where the given print page begins, remembering where we came
+    /// from. Returns whether the book has that page
+    pub fn goto_print_page(&mut self, label: &str) -> Result<bool, Error> {
+        let Some(target) = self.book.print_page(label) else {
+            return Ok(false);
+        };
+        self.history
+            .push((self.current_chapter.clone(), self.current_page));
+        self.goto(&target)?;
+        // markers without an id are only found by their label
+        if let Some(page) = self.pages.iter().position(|p| {
+            p.print_pages()
+                .iter()
+                .any(|l| l.eq_ignore_ascii_case(label))
+        }) {
+            self.current_page = page;
+        }
+        Ok(true)
+    }
+
+    /// The print page the current page is part of: the last one to begin at or before it
+    pub fn print_page(&self) -> Option<&str> {
+        let on_screen = self
+            .pages
+            .iter()
+            .take(self.current_page + 1)
+            .rev()
+            .find_map(|p| p.print_pages().last());
+        if let Some(label) = on_screen {
+            return Some(label);
+        }
+        self.book
+            .print_pages()
+            .into_iter()
+            .rev()
+            .find(|(_, location)| self.compare_location(location) != Ordering::Greater)
+            .map(|(label, _)| label)
+    }
+
+    /// The note a link points to, laid out as a panel, if the target is one
+    fn note_panel(&mut self, target: &Location, noteref: bool) -> Result<Option<Page>, Error> {
+        let Some(fragment) = target.fragment() else {
+            return Ok(None);
+        };
+        let content = self.book.section(target)?;
+        let Some(note) = content.node().find_by_id(fragment) else {
+            return Ok(None);
+        };
+        if !noteref && !note.element().is_some_and(|e| e.is_note()) {
+            return Ok(None);
+        }
+        let c = self.config.read().map_err(|_| Error::RwLock)?;
+        let pages = paginate(note, target.path(), &c, self.book.as_mut())?;
+        match pages.into_iter().next() {
+            Some(page) => Ok(Some(page.into_panel(&c)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Return to where we were before following the last link
+    pub fn back(&mut self) -> Result<(), Error> {
+        let (chapter, page) = self
+            .history
+            .pop()
+            .ok_or(DocumentError::SectionNotFound("[HISTORY]".to_owned()))?;
+        match chapter {
+            Some(elem) => {
+                self.goto(&elem.with_fragment(None))?;
+                self.current_page = page.min(self.pages.len().saturating_sub(1));
+            }
+            None => self.show_cover()?,
+        }
+        Ok(())
+    }
+
+    /// Jump to the first toc entry after the current page
+    pub fn next_chapter(&mut self) -> Result<(), Error> {
+        let target = self
+            .book
+            .toc()
+            .iter()
+            .filter_map(|e| e.location())
+            .find(|e| self.compare_location(e) == Ordering::Greater)
+            .cloned()
+            .ok_or(DocumentError::SectionNotFound("[NEXT CHAPTER]".to_owned()))?;
+        self.goto(&target)
+    }
+
+    /// Jump to the start of the current toc entry, or the previous one if already there
+    pub fn prev_chapter(&mut self) -> Result<(), Error> {
+        let target = self
+            .book
+            .toc()
+            .iter()
+            .filter_map(|e| e.location())
+            .filter(|e| self.compare_location(e) == Ordering::Less)
+            .last()
+            .cloned()
+            .ok_or(DocumentError::SectionNotFound("[PREV CHAPTER]".to_owned()))?;
+        self.goto(&target)
+    }
+
+    /// Where a location lies relative to the current page
+    fn compare_location(&self, elem: &Location) -> Ordering {
+        let Some(current) = self.current_chapter.as_ref() else {
+            // everything comes after the cover
+            return Ordering::Greater;
+        };
+        let position = self.book.position(elem.id());
+        match position.cmp(&self.book.position(current.id())) {
+            Ordering::Equal => {
+                let page = elem
+                    .fragment()
+                    .and_then(|f| self.anchor_page(f))
+                    .unwrap_or(0);
+                page.cmp(&self.current_page)
+            }
+            o => o,
+        }
+    }
+
+    fn anchor_page(&self, id: &str) -> Option<usize> {
+        self.pages.iter().position(|p| p.has_anchor(id))
+    }
+
     pub fn next_page(&mut self) -> Result<(), Error> {
+        self.note = None;
         let num_pages = self.pages.len();
         if num_pages > 0 && self.current_page < num_pages - 1 {
             self.current_page += 1;
         } else {
             // last page so get new content
-            let Content { item, node } = match self.current_chapter.as_ref() {
+            let Section {
+                location: item,
+                node,
+            } = match self.current_chapter.as_ref() {
                 Some(elem) => self.book.next(elem.id()),
-                None => self.book.first(),
+                None => self.first_content(),
             }?;
 
             let c = self.config.read().map_err(|_| Error::RwLock)?;
-            let pages = paginate(&node, &c, &mut self.book)?;
+            let pages = paginate(&node, item.path(), &c, self.book.as_mut())?;
             self.pages = pages;
             self.current_chapter = Some(item);
             self.current_page = 0;
@@ -74,19 +370,35 @@ impl BookHandler {
     }
 
     pub fn prev_page(&mut self) -> Result<(), Error> {
+        self.note = None;
         if self.current_page > 0 {
             self.current_page -= 1;
         } else {
             // first page so get new content
-            let Content { item, node } = match self.current_chapter.as_ref() {
-                Some(elem) => self.book.prev(elem.id()),
-                None => self.book.first(),
-            }?;
+            let Some(elem) = self.current_chapter.as_ref() else {
+                return Err(DocumentError::SectionNotFound("[COVER]".to_owned()).into());
+            };
+            let content = self.book.prev(elem.id());
+
+            // step back onto the cover from the start of the book
+            let before_start = match &content {
+                Ok(c) => self.book.cover_page() == Some(c.location.id()),
+                Err(DocumentError::SectionNotFound(_)) => true,
+                Err(_) => false,
+            };
+            if before_start && self.cover.is_some() {
+                return self.show_cover();
+            }
+
+            let Section {
+                location: item,
+                node,
+            } = content?;
             let c = self.config.read().map_err(|_| Error::RwLock)?;
-            let pages = paginate(&node, &c, &mut self.book)?;
+            let pages = paginate(&node, item.path(), &c, self.book.as_mut())?;
             self.pages = pages;
             self.current_chapter = Some(item);
-            self.current_page = self.pages.len() - 1;
+            self.current_page = self.pages.len().saturating_sub(1);
         }
         Ok(())
     }
@@ -4,6 +4,7 @@ use std::io::Write;
 use std::path::PathBuf;
 
 use crate::app::Error;
+use crate::epub::{Limits, RenditionSelector};
 
 const APP_NAME: &str = "aristotle";
 const CONF_FILE: &str = "config.toml";
@@ -17,6 +18,15 @@ pub struct Config {
     pub vertical_margin: u8,
     pub page_width: usize,
     pub page_height: usize,
+    /// use the fonts a book ships with instead of `family`, when it has any
+    #[serde(default)]
+    pub embedded_fonts: bool,
+    /// bounds on the books we're willing to open
+    #[serde(default)]
+    pub limits: Limits,
+    /// which rendition to read in books that have several
+    #[serde(default)]
+    pub rendition: RenditionSelector,
 }
 impl Config {
     pub fn load_config() -> Result<Self, Error> {
@@ -47,6 +57,9 @@ impl Config {
             vertical_margin: 16,
             page_width: 600,
             page_height: 800,
+            embedded_fonts: false,
+            limits: Limits::default(),
+            rendition: RenditionSelector::default(),
         }
     }
 }
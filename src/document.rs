@@ -0,0 +1,115 @@
+mod details;
+mod error;
+mod location;
+mod toc;
+
+use std::path::Path;
+
+use crate::draw::Image;
+use crate::epub::{self, Limits, PageProgression, RenditionSelector};
+use crate::fb2;
+use crate::plain;
+
+pub use details::{Credit, Details};
+pub use error::DocumentError;
+pub use location::{Location, Section};
+pub use toc::{Toc, TocEntry};
+
+/// A book in any format we can lay out: sections in reading order, each parsed into a tree
+/// of nodes, along with the resources and navigation that go with them
+pub trait Document {
+    fn title(&self) -> Option<&str>;
+    fn authors(&self) -> Vec<&str>;
+    /// Descriptive metadata besides the title and authors
+    fn details(&self) -> Details<'_> {
+        Details::default()
+    }
+
+    /// The first section in reading order
+    fn first(&mut self) -> Result<Section, DocumentError>;
+    /// The section after the one with the given id in reading order
+    fn next(&mut self, id: &str) -> Result<Section, DocumentError>;
+    /// The section before the one with the given id in reading order
+    fn prev(&mut self, id: &str) -> Result<Section, DocumentError>;
+    /// The section a location is in
+    fn section(&mut self, location: &Location) -> Result<Section, DocumentError>;
+    /// Position of a section in reading order
+    fn position(&self, id: &str) -> Option<usize>;
+    /// The location a link inside the given section points to
+    fn resolve_link(&self, from: &Location, href: &str) -> Option<Location>;
+
+    /// Decode the image an href found in the section at `from` points to
+    fn image(&mut self, from: &str, href: &str) -> Result<Image, DocumentError>;
+    fn toc(&self) -> &Toc;
+
+    /// The direction pages are turned in
+    fn page_progression(&self) -> PageProgression {
+        PageProgression::default()
+    }
+    fn cover(&mut self) -> Result<Option<Image>, DocumentError> {
+        Ok(None)
+    }
+    /// The id of a leading section that only displays the cover image, if there is one.
+    /// Only known after calling [`Document::cover`]
+    fn cover_page(&self) -> Option<&str> {
+        None
+    }
+    /// The names and contents of the fonts embedded in the document
+    fn fonts(&mut self) -> Vec<(String, Vec<u8>)> {
+        vec![]
+    }
+    /// Where reading starts, when it's not at the first section
+    fn start(&self) -> Option<&Location> {
+        None
+    }
+    /// The table of contents as a section of the document
+    fn contents(&self) -> Option<&Location> {
+        None
+    }
+    /// The book's landmarks, such as where the body matter begins, as their type and label
+    fn landmarks(&self) -> Vec<(&str, &str)> {
+        vec![]
+    }
+    /// The labels of the print edition's pages and where they begin
+    fn print_pages(&self) -> Vec<(&str, &Location)> {
+        vec![]
+    }
+    /// Where the print page with the given label begins
+    fn print_page(&mut self, _label: &str) -> Option<Location> {
+        None
+    }
+}
+
+/// Open a document in whichever format the path holds, going by its extension. Anything
+/// that isn't recognised is read as an epub
+pub fn open<P: AsRef<Path>>(
+    path: &P,
+    limits: Limits,
+    rendition: &RenditionSelector,
+) -> Result<Box<dyn Document>, DocumentError> {
+    let name = path
+        .as_ref()
+        .file_name()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if name.ends_with(".fb2") || name.ends_with(".fb2.zip") {
+        return Ok(Box::new(fb2::Book::new(path, limits)?));
+    }
+    if [".txt", ".md", ".markdown"]
+        .iter()
+        .any(|e| name.ends_with(e))
+    {
+        return Ok(Box::new(plain::Book::new(path, limits)?));
+    }
+
+    let book = epub::Book::new(path, limits)?;
+    if book.renditions().len() > 1 {
+        let labels: Vec<_> = book
+            .renditions()
+            .iter()
+            .map(|r| r.label.as_deref().unwrap_or(&r.full_path))
+            .collect();
+        tracing::info!("book has renditions {}", labels.join(", "));
+    }
+    Ok(Box::new(book.select_rendition(rendition)?))
+}
@@ -0,0 +1,79 @@
+use std::fmt::Display;
+
+/// Descriptive metadata beyond the title and authors, as much of it as the format records
+#[derive(Debug, Default)]
+pub struct Details<'a> {
+    /// Titles after the main one, such as a subtitle
+    pub other_titles: Vec<&'a str>,
+    pub languages: Vec<&'a str>,
+    /// Everyone credited besides the authors
+    pub credits: Vec<Credit<'a>>,
+    pub publisher: Option<&'a str>,
+    pub date: Option<&'a str>,
+    /// When the document was last changed
+    pub modified: Option<&'a str>,
+    pub isbn: Option<&'a str>,
+    pub identifiers: Vec<&'a str>,
+    pub subjects: Vec<&'a str>,
+    /// The series name and the book's position in it
+    pub series: Option<(&'a str, Option<f32>)>,
+    pub description: Option<&'a str>,
+}
+
+#[derive(Debug)]
+pub struct Credit<'a> {
+    pub name: &'a str,
+    /// The name to sort by, if it differs from the display name
+    pub sort_name: Option<&'a str>,
+    /// MARC relator code, e.g. `edt`, `trl`
+    pub role: Option<&'a str>,
+}
+
+impl Display for Details<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for title in &self.other_titles {
+            writeln!(f, "also titled: {}", title)?;
+        }
+        for credit in &self.credits {
+            write!(
+                f,
+                "{}: {}",
+                credit.role.unwrap_or("contributor"),
+                credit.name
+            )?;
+            match credit.sort_name {
+                Some(sort_name) => writeln!(f, " ({})", sort_name)?,
+                None => writeln!(f)?,
+            }
+        }
+        for language in &self.languages {
+            writeln!(f, "language: {}", language)?;
+        }
+        let fields = [
+            ("publisher", self.publisher),
+            ("date", self.date),
+            ("modified", self.modified),
+            ("isbn", self.isbn),
+        ];
+        for (name, value) in fields {
+            if let Some(value) = value {
+                writeln!(f, "{}: {}", name, value)?;
+            }
+        }
+        for identifier in &self.identifiers {
+            writeln!(f, "identifier: {}", identifier)?;
+        }
+        if !self.subjects.is_empty() {
+            writeln!(f, "subjects: {}", self.subjects.join(", "))?;
+        }
+        match self.series {
+            Some((name, Some(index))) => writeln!(f, "series: {} #{}", name, index)?,
+            Some((name, None)) => writeln!(f, "series: {}", name)?,
+            None => (),
+        }
+        if let Some(description) = self.description {
+            writeln!(f, "description: {}", description)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,20 @@
+use thiserror::Error;
+
+use crate::epub::EpubError;
+use crate::fb2::Fb2Error;
+use crate::plain::PlainError;
+
+#[derive(Debug, Error)]
+pub enum DocumentError {
+    #[error("epub")]
+    Epub(#[from] EpubError),
+
+    #[error("fb2")]
+    Fb2(#[from] Fb2Error),
+
+    #[error("text")]
+    Plain(#[from] PlainError),
+
+    #[error("section not found: {0}")]
+    SectionNotFound(String),
+}
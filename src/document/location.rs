@@ -0,0 +1,55 @@
+use crate::epub::Node;
+
+/// A place in a document: a section in reading order, and optionally an element inside it
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Location {
+    id: String,
+    path: String,
+    fragment: Option<String>,
+    linear: bool,
+}
+impl Location {
+    /// The start of the section with the given id, found at `path` in the document
+    pub fn new(id: String, path: String, linear: bool) -> Self {
+        Self {
+            id,
+            path,
+            fragment: None,
+            linear,
+        }
+    }
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    /// Where the section is in the document, which hrefs inside it are relative to
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+    /// The id of an element inside the section, for locations that don't start at the top
+    pub fn fragment(&self) -> Option<&str> {
+        self.fragment.as_deref()
+    }
+    /// Whether the section is part of the main reading order, rather than only reached
+    /// through links
+    pub fn is_linear(&self) -> bool {
+        self.linear
+    }
+    pub fn with_fragment(&self, fragment: Option<String>) -> Self {
+        Self {
+            fragment,
+            ..self.clone()
+        }
+    }
+}
+
+/// A section of a document, parsed and ready to be laid out
+#[derive(Debug)]
+pub struct Section {
+    pub location: Location,
+    pub node: Node,
+}
+impl Section {
+    pub fn node(&self) -> &Node {
+        &self.node
+    }
+}
@@ -0,0 +1,56 @@
+use super::Location;
+
+#[derive(Debug, Default)]
+pub struct Toc {
+    entries: Vec<TocEntry>,
+}
+impl Toc {
+    pub fn new(entries: Vec<TocEntry>) -> Self {
+        Self { entries }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn entries(&self) -> &[TocEntry] {
+        &self.entries
+    }
+
+    /// Depth-first iterator over every entry in the tree
+    pub fn iter(&self) -> impl Iterator<Item = &TocEntry> {
+        let mut stack: Vec<&TocEntry> = self.entries.iter().rev().collect();
+        std::iter::from_fn(move || {
+            let entry = stack.pop()?;
+            stack.extend(entry.children.iter().rev());
+            Some(entry)
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TocEntry {
+    label: String,
+    location: Option<Location>,
+    children: Vec<TocEntry>,
+}
+impl TocEntry {
+    pub fn new(label: String, location: Option<Location>, children: Vec<TocEntry>) -> Self {
+        Self {
+            label,
+            location,
+            children,
+        }
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+    /// The section the entry points to, carrying the fragment if there is one
+    pub fn location(&self) -> Option<&Location> {
+        self.location.as_ref()
+    }
+    pub fn children(&self) -> &[TocEntry] {
+        &self.children
+    }
+}
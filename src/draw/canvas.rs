@@ -4,7 +4,7 @@ use winit::{dpi::PhysicalSize, window::Window};
 
 use crate::text::{
     fonts::Family,
-    geom::{Point, Rect},
+    geom::{Bounds, Point, Rect},
     typeset::TypesetText,
 };
 
@@ -46,11 +46,24 @@ impl Canvas {
         Ok(())
     }
 
+    /// Fill an area with white
+    pub fn clear(&mut self, bounds: &Bounds) -> Result<(), Error> {
+        let mut buffer = self.surface.buffer_mut()?;
+
+        let max_x = cmp::min(bounds.max.x as usize, self.size.width);
+        let max_y = cmp::min(bounds.max.y as usize, self.size.height);
+        for y in bounds.min.y as usize..max_y {
+            let py = y * self.size.width;
+            buffer[py + bounds.min.x as usize..py + max_x].fill(0x00ffffff);
+        }
+        Ok(())
+    }
+
     pub fn draw_line(&mut self, start: &Point, end: &Point) -> Result<(), Error> {
         let mut buffer = self.surface.buffer_mut()?;
 
         for y in start.y as usize..end.y as usize {
-            let py = y * self.size.width as usize;
+            let py = y * self.size.width;
             for x in start.x as usize..end.x as usize {
                 let idx = py + x;
                 buffer[idx] = 0;
@@ -117,7 +130,7 @@ impl Canvas {
 
                     let z = byte as u32;
                     let c = z | z << 8 | z << 16;
-                    let idx = x as usize + y as usize * self.size.width as usize;
+                    let idx = x as usize + y as usize * self.size.width;
                     buffer[idx] = c;
                 });
             }
@@ -133,7 +146,7 @@ impl Canvas {
 
         let mut buffer = self.surface.buffer_mut()?;
 
-        let ps = img.pixel_size as usize;
+        let ps = img.pixel_size;
         // draw
         (0..rows).for_each(|rownum| {
             let line_start = (pos_y + rownum) * self.size.width + pos_x;
@@ -145,9 +158,13 @@ impl Canvas {
             {
                 let idx = line_start + i;
                 // convert to greyscale
-                let pixel: u32 = pixel[0] as u32 * 299 / 1000
-                    + pixel[1] as u32 * 587 / 1000
-                    + pixel[2] as u32 * 114 / 1000;
+                let pixel: u32 = if ps < 3 {
+                    pixel[0] as u32
+                } else {
+                    pixel[0] as u32 * 299 / 1000
+                        + pixel[1] as u32 * 587 / 1000
+                        + pixel[2] as u32 * 114 / 1000
+                };
                 buffer[idx] = pixel << 16 | pixel << 8 | pixel;
             }
         });
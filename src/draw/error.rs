@@ -15,4 +15,10 @@ pub enum Error {
 
     #[error("png")]
     Png(#[from] png::DecodingError),
+
+    #[error("jpeg")]
+    Jpeg(#[from] jpeg_decoder::Error),
+
+    #[error("unsupported image format")]
+    UnsupportedImage,
 }
@@ -1,9 +1,14 @@
 use std::io::Read;
 
+use jpeg_decoder::PixelFormat;
+
 use crate::text::geom::Rect;
 
 use super::Error;
 
+const PNG_SIGNATURE: &[u8] = b"\x89PNG";
+const JPEG_SIGNATURE: &[u8] = b"\xff\xd8\xff";
+
 #[derive(Debug)]
 pub struct Image {
     data: Vec<u8>,
@@ -12,6 +17,20 @@ pub struct Image {
     pub size: Rect,
 }
 impl Image {
+    /// Media types of the images we can decode
+    pub const MEDIATYPES: [&'static str; 2] = ["image/png", "image/jpeg"];
+
+    /// Decode an image, detecting the format from its signature
+    pub fn from_bytes(data: &[u8]) -> Result<Self, Error> {
+        if data.starts_with(PNG_SIGNATURE) {
+            Self::open(data)
+        } else if data.starts_with(JPEG_SIGNATURE) {
+            Self::open_jpeg(data)
+        } else {
+            Err(Error::UnsupportedImage)
+        }
+    }
+
     pub fn open<R: Read>(data: R) -> Result<Self, Error> {
         // open png
         let decoder = png::Decoder::new(data);
@@ -36,6 +55,38 @@ impl Image {
             size,
         })
     }
+    pub fn open_jpeg<R: Read>(data: R) -> Result<Self, Error> {
+        let mut decoder = jpeg_decoder::Decoder::new(data);
+        let mut data = decoder.decode()?;
+        let info = decoder.info().ok_or(Error::UnsupportedImage)?;
+        let pixel_size = match info.pixel_format {
+            PixelFormat::L8 => 1,
+            PixelFormat::RGB24 => 3,
+            PixelFormat::CMYK32 => {
+                data = cmyk_to_rgb(&data);
+                3
+            }
+            PixelFormat::L16 => return Err(Error::UnsupportedImage),
+        };
+        let size = Rect {
+            width: info.width as usize,
+            height: info.height as usize,
+        };
+        Ok(Self {
+            data_len: data.len(),
+            data,
+            pixel_size,
+            size,
+        })
+    }
+
+    /// Scale factor that fits the image inside `bounds` while keeping its aspect ratio
+    pub fn fit_scale(&self, bounds: Rect) -> f32 {
+        let horizontal = bounds.width as f32 / self.size.width as f32;
+        let vertical = bounds.height as f32 / self.size.height as f32;
+        horizontal.min(vertical)
+    }
+
     pub fn rescale(&self, scale: f32) -> Self {
         let ps = self.pixel_size;
         let new_width = (self.size.width as f32 * scale) as usize;
@@ -52,7 +103,7 @@ impl Image {
             }
         }
         let data_len = new_data.len();
-        return Self {
+        Self {
             data: new_data,
             data_len,
             size: Rect {
@@ -60,9 +111,22 @@ impl Image {
                 height: new_height,
             },
             pixel_size: self.pixel_size,
-        };
+        }
     }
     pub fn data(&self) -> &[u8] {
         &self.data[..self.data_len]
     }
 }
+
+fn cmyk_to_rgb(data: &[u8]) -> Vec<u8> {
+    data.chunks_exact(4)
+        .flat_map(|p| {
+            let k = 255 - p[3] as u32;
+            [
+                ((255 - p[0] as u32) * k / 255) as u8,
+                ((255 - p[1] as u32) * k / 255) as u8,
+                ((255 - p[2] as u32) * k / 255) as u8,
+            ]
+        })
+        .collect()
+}
@@ -1,17 +1,35 @@
 mod book;
-mod content;
+mod container;
+mod diagnostics;
+mod encoding;
+mod encryption;
 mod error;
+mod guide;
+mod href;
 mod html;
 mod index;
+mod landmarks;
+mod limits;
 mod manifest;
 mod metadata;
+mod package;
+mod page_list;
+mod source;
 mod spine;
+mod toc;
 mod zip;
 
 pub use book::Book;
 pub use error::EpubError;
 
-pub use content::Content;
+pub use container::RenditionSelector;
+pub use diagnostics::Severity;
+pub use encoding::decode;
+pub use href::resolve_path;
+pub use html::Element;
 pub use html::ElementVariant;
 pub use html::Node;
-pub use index::IndexElement;
+pub use limits::Limits;
+pub use source::Source;
+pub use spine::PageProgression;
+pub use zip::ZipSource;
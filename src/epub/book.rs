@@ -1,128 +1,863 @@
 use std::{
+    collections::HashMap,
     fs::File,
-    io::Read,
-    path::{Path, PathBuf},
+    io::{Read, Seek},
+    path::Path,
 };
 
-use quick_xml::{events::Event, Reader};
-use zip::ZipArchive;
-
-use crate::epub::spine::Spine;
+use crate::document::{Credit, Details, Document, DocumentError, Location, Section, Toc};
+use crate::draw::{self, Image};
 
 use super::{
-    content::Content,
+    container::{read_rootfiles, RenditionSelector, Rootfile},
+    diagnostics::{self, Diagnostic},
+    encoding::decode,
+    encryption::Encryption,
     error::EpubError,
-    index::{Index, IndexElement},
+    guide::Guide,
+    href,
+    html::Node,
+    index::Index,
+    landmarks::Landmarks,
+    limits::Limits,
     manifest::Manifest,
-    metadata::Metadata,
-    zip::{find_rootfile, read_document},
+    metadata::{IdentifierScheme, Metadata},
+    package::Package,
+    page_list::PageList,
+    source::{DirSource, Source},
+    spine::PageProgression,
+    toc,
+    zip::ZipSource,
 };
 
+const NCX_MEDIATYPE: &str = "application/x-dtbncx+xml";
+/// Media types of embedded TrueType, OpenType and WOFF fonts, including the unofficial ones
+/// older books use
+pub(super) const FONT_MEDIATYPES: [&str; 14] = [
+    "font/otf",
+    "font/ttf",
+    "font/sfnt",
+    "application/vnd.ms-opentype",
+    "application/font-sfnt",
+    "application/x-font-otf",
+    "application/x-font-opentype",
+    "application/x-font-ttf",
+    "application/x-font-truetype",
+    "font/woff",
+    "font/woff2",
+    "application/font-woff",
+    "application/font-woff2",
+    "application/x-font-woff",
+];
+
 #[derive(Debug, Default)]
 pub struct Book {
-    source_zip: Option<ZipArchive<File>>,
+    source: Option<Box<dyn Source>>,
     index: Index,
     metadata: Metadata,
-    contents_dir: PathBuf,
+    toc: Toc,
+    landmarks: Landmarks,
+    page_list: PageList,
+    /// the inline page break markers by their lowercased labels, found the first time a
+    /// print page is looked for in a book without a page list
+    page_breaks: Option<HashMap<String, Location>>,
+    guide: Guide,
+    cover_candidates: Vec<String>,
+    cover_page: Option<String>,
+    /// the path the cover was decoded from, once it's been looked for
+    cover: Option<Option<String>>,
+    fonts: Vec<String>,
+    encryption: Encryption,
+    manifest: Manifest,
+    package_path: String,
+    rootfiles: Vec<Rootfile>,
+    limits: Limits,
     content_buffer: Vec<u8>,
 }
 
 impl Book {
-    pub fn new<P>(path: &P) -> Result<Self, EpubError>
+    /// Open a packed book, or an unpacked one if the path is a directory
+    pub fn new<P>(path: &P, limits: Limits) -> Result<Self, EpubError>
     where
         P: AsRef<Path>,
     {
+        if path.as_ref().is_dir() {
+            return Self::from_dir(path, limits);
+        }
         let epub_file = File::open(path)?;
-        let mut epub = ZipArchive::new(epub_file)?;
-
-        // read META-INF
-        let mut file_bytes = Vec::new();
-        {
-            let mut file = epub.by_name("META-INF/container.xml")?;
-            let _ = file.read_to_end(&mut file_bytes)?;
-        }
-        let file_contents = std::str::from_utf8(&file_bytes)?;
-
-        // open the rootfile
-        let rootfile_path = find_rootfile(file_contents)?;
-        let contents_dir = rootfile_path
-            .parent()
-            .unwrap_or(Path::new("OEBPS"))
-            .to_owned();
-
-        // parse the rootfile contents
-        let rootfile_path = rootfile_path.to_str().unwrap();
-        read_document(&mut epub, rootfile_path, &mut file_bytes)?;
-        let rootfile_contents = std::str::from_utf8(&file_bytes)?;
-        let mut reader = Reader::from_str(rootfile_contents);
-
-        let mut book = Book::default();
-
-        let mut manifest = Manifest::default();
-        let mut spine = Spine::default();
-
-        loop {
-            match reader.read_event() {
-                Ok(Event::Start(ref e)) => match e.name().as_ref() {
-                    b"metadata" => {
-                        let metadata = Metadata::extract(&mut reader)?;
-                        book.metadata = metadata;
-                    }
-                    b"manifest" => {
-                        manifest = Manifest::extract(&mut reader)?;
-                    }
-                    b"spine" => {
-                        spine = Spine::extract(&mut reader)?;
-                    }
-                    _ => (),
-                },
-                Ok(Event::Eof) => break, // exits the loop when reaching end of file
-                _ => (),
-            }
+        Self::from_reader(epub_file, limits)
+    }
+
+    /// Check a book for problems without opening it for reading, so that books that don't
+    /// open at all can be diagnosed too
+    pub fn diagnose<P>(path: &P, limits: Limits) -> Vec<Diagnostic>
+    where
+        P: AsRef<Path>,
+    {
+        match open_source(path, &limits) {
+            Ok(mut source) => diagnostics::diagnose(source.as_mut(), &limits),
+            Err(e) => vec![Diagnostic::error(None, e.to_string())],
+        }
+    }
+
+    /// Open a packed book from any seekable stream, such as one held in memory
+    pub fn from_reader<R>(reader: R, limits: Limits) -> Result<Self, EpubError>
+    where
+        R: Read + Seek + 'static,
+    {
+        let source = ZipSource::new(reader, &limits)?;
+        Self::from_source(Box::new(source), limits)
+    }
+
+    /// Open an unpacked book, laid out in a directory as it would be in the zip archive
+    pub fn from_dir<P>(path: &P, limits: Limits) -> Result<Self, EpubError>
+    where
+        P: AsRef<Path>,
+    {
+        Self::from_source(Box::new(DirSource::new(path, &limits)), limits)
+    }
+
+    /// Open the default rendition
+    fn from_source(mut epub: Box<dyn Source>, limits: Limits) -> Result<Self, EpubError> {
+        let rootfiles = read_rootfiles(epub.as_mut())?;
+        let rootfile = RenditionSelector::default()
+            .select(&rootfiles)
+            .ok_or(EpubError::Rootfile)?
+            .full_path
+            .clone();
+        Self::from_package(epub, rootfiles, &rootfile, limits)
+    }
+
+    /// The renditions the container lists, the default one first
+    pub fn renditions(&self) -> &[Rootfile] {
+        &self.rootfiles
+    }
+
+    /// Switch to the rendition that best matches the selector. Books with a single
+    /// rendition, or none the selector can pick, are returned as they are
+    pub fn select_rendition(mut self, selector: &RenditionSelector) -> Result<Self, EpubError> {
+        if self.rootfiles.len() < 2 {
+            return Ok(self);
         }
+        let Some(rootfile) = selector.select(&self.rootfiles) else {
+            tracing::warn!("no rendition matches {:?}, reading the default", selector);
+            return Ok(self);
+        };
+        if rootfile.full_path == self.package_path {
+            return Ok(self);
+        }
+        let path = rootfile.full_path.clone();
+        tracing::info!("reading rendition {}", path);
+        let source = self.source.take().unwrap();
+        Self::from_package(
+            source,
+            std::mem::take(&mut self.rootfiles),
+            &path,
+            self.limits,
+        )
+    }
 
-        book.index = Index::new(manifest, spine, &contents_dir);
-        book.source_zip = Some(epub);
-        book.contents_dir = contents_dir;
+    fn from_package(
+        mut epub: Box<dyn Source>,
+        rootfiles: Vec<Rootfile>,
+        rootfile: &str,
+        limits: Limits,
+    ) -> Result<Self, EpubError> {
+        let Package {
+            path: rootfile_path,
+            unique_identifier,
+            metadata,
+            manifest,
+            spine,
+            guide,
+        } = Package::read(epub.as_mut(), rootfile)?;
+        let rootfile_path = rootfile_path.as_str();
+
+        let mut book = Book {
+            metadata,
+            guide,
+            ..Default::default()
+        };
+
+        // manifest hrefs are relative to the package document
+        let resolve = |href: &str| href::resolve_path(rootfile_path, href);
+        let nav_path = manifest
+            .item_with_property("nav")
+            .and_then(|item| resolve(&item.href));
+        let ncx_path = spine
+            .toc
+            .as_deref()
+            .and_then(|id| manifest.item(id))
+            .or_else(|| manifest.item_with_mediatype(NCX_MEDIATYPE))
+            .and_then(|item| resolve(&item.href));
+        book.cover_candidates = cover_candidates(&manifest, &book.metadata, &book.guide)
+            .iter()
+            .filter_map(|href| resolve(href))
+            .collect();
+        book.cover_candidates.dedup();
+        book.fonts = manifest
+            .items_with_mediatypes(&FONT_MEDIATYPES)
+            .filter_map(|item| resolve(&item.href))
+            .collect();
+        book.encryption = read_encryption(epub.as_mut(), &book.metadata, unique_identifier);
+        book.index = Index::new(&manifest, spine, rootfile_path);
+        let max_depth = limits.max_depth;
+        book.toc = read_toc(
+            epub.as_mut(),
+            &book.index,
+            nav_path.clone(),
+            ncx_path.clone(),
+            max_depth,
+        );
+        book.page_list = read_page_list(
+            epub.as_mut(),
+            &book.index,
+            nav_path.clone(),
+            ncx_path,
+            max_depth,
+        );
+        book.landmarks = read_landmarks(epub.as_mut(), &book.index, nav_path, max_depth)
+            .unwrap_or_else(|| Landmarks::from_guide(&book.guide, rootfile_path, &book.index));
+
+        book.source = Some(epub);
+        book.manifest = manifest;
+        book.package_path = rootfile_path.to_owned();
+        book.rootfiles = rootfiles;
+        book.limits = limits;
         Ok(book)
     }
 
-    pub fn file(&mut self, href: &str) -> Result<&[u8], EpubError> {
-        let mut path = PathBuf::from(href);
-        let parent = path.parent().unwrap();
-        if parent != self.contents_dir {
-            path = self.contents_dir.join(&path);
+    fn cover_image(&mut self, path: &str) -> Result<Option<Image>, EpubError> {
+        let path = self.renderable(path, &Image::MEDIATYPES);
+        let limits = self.limits;
+        let data = self.resource(&path)?;
+        match Image::from_bytes(data) {
+            Ok(image) => return Ok(Some(image)),
+            Err(draw::Error::UnsupportedImage) => (),
+            Err(e) => return Err(e.into()),
         }
-        let href = path.to_str().unwrap();
-        let zip = self.source_zip.as_mut().unwrap();
-        read_document(zip, href, &mut self.content_buffer)?;
+
+        // not an image, so it may be a page wrapping one
+        let node = Node::new(data, &limits)?;
+        let Some((href, _)) = node.first_image() else {
+            return Ok(None);
+        };
+        let href = href.to_owned();
+        Ok(Some(self.decode_image(&path, &href)?))
+    }
+
+    /// Images in a format we can't decode are replaced by their first fallback that we can
+    fn decode_image(&mut self, from: &str, href: &str) -> Result<Image, EpubError> {
+        let path = href::resolve_path(from, href)
+            .ok_or_else(|| EpubError::MissingResource(href.to_owned()))?;
+        let path = self.renderable(&path, &Image::MEDIATYPES);
+        Ok(Image::from_bytes(self.resource(&path)?)?)
+    }
+
+    /// The path of the best representation of a resource: the first item of its manifest
+    /// fallback chain with one of the given media types. Resources outside the manifest, or
+    /// without such a fallback, are used as they are
+    fn renderable(&self, path: &str, mediatypes: &[&str]) -> String {
+        let item_path = |href: &str| href::resolve_path(&self.package_path, href);
+        let Some(item) = self
+            .manifest
+            .items()
+            .find(|i| item_path(&i.href).as_deref() == Some(path))
+        else {
+            return path.to_owned();
+        };
+        self.manifest
+            .fallback_with_mediatypes(item, mediatypes)
+            .and_then(|i| item_path(&i.href))
+            .unwrap_or_else(|| path.to_owned())
+    }
+
+    /// Read a file by its full path inside the container
+    fn resource(&mut self, path: &str) -> Result<&[u8], EpubError> {
+        let source = self.source.as_mut().unwrap();
+        source.read(path, &mut self.content_buffer)?;
+        self.encryption.deobfuscate(path, &mut self.content_buffer);
         Ok(&self.content_buffer)
     }
 
-    pub fn content(&mut self, elem: &IndexElement) -> Result<Content, EpubError> {
-        let data = self.file(elem.path())?;
-        Content::new(elem, &data).map_err(|e| e.into())
+    /// Read the whole spine for inline page break markers. Where a label is repeated, the
+    /// first marker wins
+    fn find_page_breaks(&mut self) -> HashMap<String, Location> {
+        let mut page_breaks = HashMap::new();
+        let items: Vec<_> = self.index.iter().cloned().collect();
+        for item in items {
+            let content = match self.content(&item) {
+                Ok(content) => content,
+                Err(e) => {
+                    tracing::warn!("unable to search {} for page breaks: {}", item.path(), e);
+                    continue;
+                }
+            };
+            for elem in content.node().iter().filter_map(Node::element) {
+                if let Some(label) = elem.page_break() {
+                    let id = elem.id().map(str::to_owned);
+                    page_breaks
+                        .entry(label.trim().to_lowercase())
+                        .or_insert_with(|| item.with_fragment(id));
+                }
+            }
+        }
+        page_breaks
+    }
+
+    fn content(&mut self, location: &Location) -> Result<Section, EpubError> {
+        let limits = self.limits;
+        let data = self.resource(location.path())?;
+        Ok(Section {
+            location: location.clone(),
+            node: Node::new(data, &limits)?,
+        })
+    }
+}
+
+impl Document for Book {
+    fn title(&self) -> Option<&str> {
+        self.metadata.title()
+    }
+
+    fn authors(&self) -> Vec<&str> {
+        self.metadata.authors().map(|a| a.name()).collect()
     }
 
-    pub fn first(&mut self) -> Result<Content, EpubError> {
+    fn details(&self) -> Details<'_> {
+        let metadata = &self.metadata;
+        let authors: Vec<_> = metadata.authors().collect();
+        let credits = metadata
+            .creators()
+            .iter()
+            .chain(metadata.contributors())
+            .filter(|c| !authors.contains(c))
+            .map(|c| Credit {
+                name: c.name(),
+                sort_name: Some(c.sort_name()).filter(|s| *s != c.name()),
+                role: c.role(),
+            })
+            .collect();
+        Details {
+            other_titles: metadata
+                .titles()
+                .iter()
+                .skip(1)
+                .map(String::as_str)
+                .collect(),
+            languages: metadata.languages().iter().map(String::as_str).collect(),
+            credits,
+            publisher: metadata.publisher(),
+            date: metadata.date(),
+            modified: metadata.modified(),
+            isbn: metadata.isbn(),
+            identifiers: metadata.identifiers().iter().map(|i| i.value()).collect(),
+            subjects: metadata.subjects().iter().map(String::as_str).collect(),
+            series: metadata.series().map(|s| (s.name(), s.index())),
+            description: metadata.description(),
+        }
+    }
+
+    fn first(&mut self) -> Result<Section, DocumentError> {
         let item = self
             .index
             .first()
-            .ok_or(EpubError::ContentNotFound("[FIRST]".to_owned()))?;
-        self.content(&item)
+            .ok_or(DocumentError::SectionNotFound("[FIRST]".to_owned()))?;
+        Ok(self.content(&item)?)
     }
-    pub fn next(&mut self, cur: &str) -> Result<Content, EpubError> {
+
+    fn next(&mut self, id: &str) -> Result<Section, DocumentError> {
         let item = self
             .index
-            .next(cur)
-            .ok_or(EpubError::ContentNotFound(cur.to_owned()))?;
-        self.content(&item)
+            .next(id)
+            .ok_or(DocumentError::SectionNotFound(id.to_owned()))?;
+        Ok(self.content(&item)?)
     }
-    pub fn prev(&mut self, cur: &str) -> Result<Content, EpubError> {
+
+    fn prev(&mut self, id: &str) -> Result<Section, DocumentError> {
         let item = self
             .index
-            .prev(cur)
-            .ok_or(EpubError::ContentNotFound(cur.to_owned()))?;
-        self.content(&item)
+            .prev(id)
+            .ok_or(DocumentError::SectionNotFound(id.to_owned()))?;
+        Ok(self.content(&item)?)
+    }
+
+    fn section(&mut self, location: &Location) -> Result<Section, DocumentError> {
+        Ok(self.content(location)?)
+    }
+
+    /// Position of a spine item in reading order
+    fn position(&self, id: &str) -> Option<usize> {
+        self.index.position(id)
+    }
+
+    fn resolve_link(&self, from: &Location, href: &str) -> Option<Location> {
+        self.index.resolve(from.path(), href)
+    }
+
+    /// Images in a format we can't decode are replaced by their first fallback that we can
+    fn image(&mut self, from: &str, href: &str) -> Result<Image, DocumentError> {
+        Ok(self.decode_image(from, href)?)
+    }
+
+    fn toc(&self) -> &Toc {
+        &self.toc
+    }
+
+    /// The direction pages are turned in, as declared by the spine
+    fn page_progression(&self) -> PageProgression {
+        self.index.progression()
+    }
+
+    /// Find and decode the cover image. Candidates are, in order, the EPUB3 `cover-image`
+    /// manifest item, the EPUB2 `<meta name="cover">`, the guide's `cover` reference, and
+    /// finally the first spine item if it's a page showing nothing but an image. The path
+    /// that worked is kept, so later calls only decode it
+    fn cover(&mut self) -> Result<Option<Image>, DocumentError> {
+        if let Some(cover) = self.cover.clone() {
+            return match cover {
+                Some(path) => Ok(self.cover_image(&path)?),
+                None => Ok(None),
+            };
+        }
+
+        let first_page = self.index.first();
+        if let Some(first) = first_page.as_ref() {
+            let limits = self.limits;
+            let data = self.resource(first.path())?;
+            if let Ok(Some((_, false))) = Node::new(data, &limits).as_ref().map(Node::first_image) {
+                self.cover_page = Some(first.id().to_owned());
+            }
+        }
+
+        let mut candidates = self.cover_candidates.clone();
+        if let Some(first) = self.cover_page.as_ref().and(first_page) {
+            candidates.push(first.path().to_owned());
+        }
+        for path in candidates {
+            match self.cover_image(&path) {
+                Ok(Some(image)) => {
+                    self.cover = Some(Some(path));
+                    return Ok(Some(image));
+                }
+                Ok(None) => (),
+                Err(e) => tracing::warn!("unable to load cover {}: {}", path, e),
+            }
+        }
+        self.cover = Some(None);
+        Ok(None)
+    }
+
+    fn cover_page(&self) -> Option<&str> {
+        self.cover_page.as_deref()
+    }
+
+    /// Fonts that can't be read are left out
+    fn fonts(&mut self) -> Vec<(String, Vec<u8>)> {
+        let mut fonts = vec![];
+        for path in self.fonts.clone() {
+            match self.resource(&path) {
+                Ok(data) => fonts.push((path, data.to_vec())),
+                Err(e) => tracing::warn!("unable to read font {}: {}", path, e),
+            }
+        }
+        fonts
+    }
+
+    /// The body matter landmark, or the guide's `text` reference
+    fn start(&self) -> Option<&Location> {
+        self.landmarks.start()
+    }
+
+    fn contents(&self) -> Option<&Location> {
+        self.landmarks.contents()
+    }
+
+    fn landmarks(&self) -> Vec<(&str, &str)> {
+        self.landmarks
+            .entries()
+            .iter()
+            .map(|l| (l.kind(), l.label()))
+            .collect()
+    }
+
+    fn print_pages(&self) -> Vec<(&str, &Location)> {
+        self.page_list
+            .entries()
+            .iter()
+            .map(|p| (p.label(), p.location()))
+            .collect()
+    }
+
+    /// Books without a page list are searched for inline page break markers
+    fn print_page(&mut self, label: &str) -> Option<Location> {
+        if let Some(page) = self.page_list.get(label) {
+            return Some(page.location().clone());
+        }
+        if self.page_breaks.is_none() {
+            self.page_breaks = Some(self.find_page_breaks());
+        }
+        let page_breaks = self.page_breaks.as_ref()?;
+        page_breaks.get(&label.trim().to_lowercase()).cloned()
+    }
+}
+
+/// The files of a packed book, or of an unpacked one if the path is a directory, as
+/// [`Book::new`] opens them
+fn open_source<P: AsRef<Path>>(path: &P, limits: &Limits) -> Result<Box<dyn Source>, EpubError> {
+    if path.as_ref().is_dir() {
+        return Ok(Box::new(DirSource::new(path, limits)));
+    }
+    let epub_file = File::open(path)?;
+    Ok(Box::new(ZipSource::new(epub_file, limits)?))
+}
+
+/// Hrefs, relative to the package document, that may point at the cover image or a page
+/// wrapping it
+fn cover_candidates(manifest: &Manifest, metadata: &Metadata, guide: &Guide) -> Vec<String> {
+    let mut candidates = vec![];
+    if let Some(item) = manifest.item_with_property("cover-image") {
+        candidates.push(item.href.clone());
+    }
+    if let Some(item) = metadata.cover().and_then(|id| manifest.item(id)) {
+        candidates.push(item.href.clone());
+    }
+    if let Some(reference) = guide.reference("cover") {
+        candidates.push(reference.href.clone());
+    }
+    candidates
+}
+
+/// Read `META-INF/encryption.xml`, if there is one, with the keys for deobfuscating fonts
+fn read_encryption(
+    epub: &mut dyn Source,
+    metadata: &Metadata,
+    unique_identifier: Option<String>,
+) -> Encryption {
+    let mut buf = Vec::new();
+    let encryption = match epub.read("META-INF/encryption.xml", &mut buf) {
+        Ok(_) => decode(&buf).and_then(|xml| Encryption::from_xml(&xml)),
+        Err(EpubError::MissingResource(_)) => return Encryption::default(),
+        Err(e) => Err(e),
+    };
+    let encryption = match encryption {
+        Ok(encryption) if !encryption.is_empty() => encryption,
+        Ok(_) => return Encryption::default(),
+        Err(e) => {
+            tracing::warn!("unable to parse encryption.xml: {}", e);
+            return Encryption::default();
+        }
+    };
+
+    let unique_identifier = unique_identifier
+        .and_then(|id| metadata.identifier(&id))
+        .map(|i| i.value());
+    // Adobe keys come from the book's UUID, which needn't be the unique identifier
+    let uuid = metadata
+        .identifiers()
+        .iter()
+        .find(|i| i.scheme() == Some(&IdentifierScheme::Uuid))
+        .map(|i| i.value())
+        .or(unique_identifier);
+    encryption.with_keys(unique_identifier, uuid)
+}
+
+/// Read the toc from the EPUB3 navigation document, falling back to the EPUB2 NCX
+fn read_toc(
+    epub: &mut dyn Source,
+    index: &Index,
+    nav_path: Option<String>,
+    ncx_path: Option<String>,
+    max_depth: usize,
+) -> Toc {
+    let mut buf = Vec::new();
+    if let Some(path) = nav_path {
+        let toc = epub
+            .read(&path, &mut buf)
+            .and_then(|_| decode(&buf))
+            .and_then(|xml| toc::from_nav(&xml, &path, index, max_depth));
+        match toc {
+            Ok(toc) if !toc.is_empty() => return toc,
+            Ok(_) => tracing::warn!("nav document has an empty toc"),
+            Err(e) => tracing::warn!("unable to parse nav document: {}", e),
+        }
+    }
+    if let Some(path) = ncx_path {
+        let toc = epub
+            .read(&path, &mut buf)
+            .and_then(|_| decode(&buf))
+            .and_then(|xml| toc::from_ncx(&xml, &path, index, max_depth));
+        match toc {
+            Ok(toc) => return toc,
+            Err(e) => tracing::warn!("unable to parse ncx document: {}", e),
+        }
+    }
+    Toc::default()
+}
+
+/// Read the page list from the EPUB3 navigation document, falling back to the EPUB2 NCX.
+/// Most books don't have one
+fn read_page_list(
+    epub: &mut dyn Source,
+    index: &Index,
+    nav_path: Option<String>,
+    ncx_path: Option<String>,
+    max_depth: usize,
+) -> PageList {
+    let mut buf = Vec::new();
+    if let Some(path) = nav_path {
+        let page_list = epub
+            .read(&path, &mut buf)
+            .and_then(|_| decode(&buf))
+            .and_then(|xml| PageList::from_nav(&xml, &path, index, max_depth));
+        match page_list {
+            Ok(page_list) if !page_list.is_empty() => return page_list,
+            Ok(_) | Err(EpubError::UnexpectedEof) => (),
+            Err(e) => tracing::warn!("unable to parse page list: {}", e),
+        }
+    }
+    if let Some(path) = ncx_path {
+        let page_list = epub
+            .read(&path, &mut buf)
+            .and_then(|_| decode(&buf))
+            .and_then(|xml| PageList::from_ncx(&xml, &path, index, max_depth));
+        match page_list {
+            Ok(page_list) => return page_list,
+            Err(EpubError::UnexpectedEof) => (),
+            Err(e) => tracing::warn!("unable to parse ncx page list: {}", e),
+        }
+    }
+    PageList::default()
+}
+
+/// Read the landmarks from the EPUB3 navigation document. `None` when there's no
+/// navigation document or it has no landmarks, so the guide should be used instead
+fn read_landmarks(
+    epub: &mut dyn Source,
+    index: &Index,
+    nav_path: Option<String>,
+    max_depth: usize,
+) -> Option<Landmarks> {
+    let path = nav_path?;
+    let mut buf = Vec::new();
+    let landmarks = epub
+        .read(&path, &mut buf)
+        .and_then(|_| decode(&buf))
+        .and_then(|xml| Landmarks::from_nav(&xml, &path, index, max_depth));
+    match landmarks {
+        Ok(landmarks) if !landmarks.is_empty() => Some(landmarks),
+        Ok(_) => None,
+        // a nav document without landmarks runs off the end looking for them
+        Err(EpubError::UnexpectedEof) => None,
+        Err(e) => {
+            tracing::warn!("unable to parse landmarks: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use crate::document::Document;
+    use crate::epub::{container::Layout, RenditionSelector};
+
+    use super::{Book, Limits};
+
+    #[test]
+    fn from_memory_and_dir() {
+        let path = "testfiles/epubs/frankenstein.epub";
+        let from_file = Book::new(&path, Limits::default()).unwrap();
+
+        let bytes = std::fs::read(path).unwrap();
+        let mut from_memory =
+            Book::from_reader(Cursor::new(bytes.clone()), Limits::default()).unwrap();
+        assert_eq!(from_memory.title(), from_file.title());
+
+        let dir = tempfile::tempdir().unwrap();
+        zip::ZipArchive::new(Cursor::new(bytes))
+            .unwrap()
+            .extract(&dir)
+            .unwrap();
+        let mut from_dir = Book::new(&dir.path(), Limits::default()).unwrap();
+        assert_eq!(from_dir.title(), from_file.title());
+        assert_eq!(
+            from_dir.first().unwrap().location,
+            from_memory.first().unwrap().location
+        );
+        assert!(from_dir.cover().unwrap().is_some());
+        // the cover is only looked for once
+        assert!(from_dir.cover.as_ref().is_some_and(Option::is_some));
+        assert!(from_dir.cover().unwrap().is_some());
+    }
+
+    #[test]
+    fn guide_landmarks() {
+        let path = "testfiles/epubs/pride_and_prejudice.epub";
+        let book = Book::new(&path, Limits::default()).unwrap();
+        assert_eq!(book.landmarks(), [("cover", "Cover")]);
+    }
+
+    #[test]
+    fn renditions() {
+        let container = r#"<container xmlns:rendition="http://www.idpf.org/2013/rendition">
+  <rootfiles>
+    <rootfile full-path="reflow.opf" rendition:layout="reflowable"/>
+    <rootfile full-path="fixed/package.opf" rendition:layout="pre-paginated"/>
+  </rootfiles>
+</container>"#;
+        let opf = |title: &str, href: &str| {
+            format!(
+                r#"<package xmlns:dc="http://purl.org/dc/elements/1.1/">
+  <metadata><dc:title>{title}</dc:title></metadata>
+  <manifest><item id="p1" href="{href}" media-type="application/xhtml+xml"/></manifest>
+  <spine><itemref idref="p1"/></spine>
+</package>"#
+            )
+        };
+        let page = "<html><body><p>page</p></body></html>";
+        let mut zip = zip::ZipWriter::new(Cursor::new(vec![]));
+        let options = zip::write::SimpleFileOptions::default();
+        for (name, contents) in [
+            ("META-INF/container.xml", container.to_owned()),
+            ("reflow.opf", opf("Reflowable", "text/p1.xhtml")),
+            ("fixed/package.opf", opf("Fixed", "p1.xhtml")),
+            ("text/p1.xhtml", page.to_owned()),
+            ("fixed/p1.xhtml", page.to_owned()),
+        ] {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+        let data = zip.finish().unwrap();
+
+        // the selector is a global setting, so it mustn't keep single rendition books from
+        // opening
+        let single = Book::new(&"testfiles/epubs/frankenstein.epub", Limits::default()).unwrap();
+        let second = RenditionSelector {
+            index: Some(1),
+            ..Default::default()
+        };
+        assert!(single.select_rendition(&second).is_ok());
+
+        let book = Book::from_reader(data, Limits::default()).unwrap();
+        assert_eq!(book.renditions().len(), 2);
+        assert_eq!(book.title(), Some("Reflowable"));
+
+        let fixed = RenditionSelector {
+            layout: Some(Layout::PrePaginated),
+            ..Default::default()
+        };
+        let mut book = book.select_rendition(&fixed).unwrap();
+        assert_eq!(book.title(), Some("Fixed"));
+        assert_eq!(book.first().unwrap().location.path(), "fixed/p1.xhtml");
+        assert_eq!(book.renditions().len(), 2);
+
+        // a selector that picks nothing leaves the book as it is
+        let missing = RenditionSelector {
+            index: Some(5),
+            ..Default::default()
+        };
+        let book = book.select_rendition(&missing).unwrap();
+        assert_eq!(book.title(), Some("Fixed"));
+    }
+
+    #[test]
+    fn details() {
+        let container = r#"<container><rootfiles>
+    <rootfile full-path="package.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles></container>"#;
+        let opf = r#"<package xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:opf="http://www.idpf.org/2007/opf">
+  <metadata>
+    <dc:title>The Iliad</dc:title>
+    <dc:creator opf:role="aut" opf:file-as="Homer">Homer</dc:creator>
+    <dc:creator opf:role="trl" opf:file-as="Butler, Samuel">Samuel Butler</dc:creator>
+    <dc:contributor>Anonymous</dc:contributor>
+    <dc:language>en</dc:language>
+    <dc:language>grc</dc:language>
+    <dc:publisher>Penguin</dc:publisher>
+    <dc:identifier opf:scheme="ISBN">9780000000002</dc:identifier>
+    <meta name="calibre:series" content="Epics"/>
+    <meta name="calibre:series_index" content="1"/>
+  </metadata>
+  <manifest><item id="c1" href="c1.xhtml" media-type="application/xhtml+xml"/></manifest>
+  <spine><itemref idref="c1"/></spine>
+</package>"#;
+        let mut zip = zip::ZipWriter::new(Cursor::new(vec![]));
+        let options = zip::write::SimpleFileOptions::default();
+        for (name, contents) in [
+            ("META-INF/container.xml", container),
+            ("package.opf", opf),
+            ("c1.xhtml", "<html><body><p>Sing</p></body></html>"),
+        ] {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+        let book = Book::from_reader(zip.finish().unwrap(), Limits::default()).unwrap();
+        assert_eq!(book.authors(), ["Homer"]);
+        let details = book.details();
+        let credits: Vec<_> = details
+            .credits
+            .iter()
+            .map(|c| (c.name, c.sort_name, c.role))
+            .collect();
+        assert_eq!(
+            credits,
+            [
+                ("Samuel Butler", Some("Butler, Samuel"), Some("trl")),
+                ("Anonymous", None, None)
+            ]
+        );
+        assert_eq!(details.languages, ["en", "grc"]);
+        assert_eq!(details.isbn, Some("9780000000002"));
+        assert_eq!(details.series, Some(("Epics", Some(1.0))));
+        assert_eq!(
+            details.to_string(),
+            "trl: Samuel Butler (Butler, Samuel)
+contributor: Anonymous
+language: en
+language: grc
+publisher: Penguin
+isbn: 9780000000002
+identifier: 9780000000002
+series: Epics #1
+"
+        );
+    }
+
+    #[test]
+    fn inline_print_pages() {
+        let container = r#"<container><rootfiles>
+    <rootfile full-path="package.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles></container>"#;
+        let opf = r#"<package>
+  <manifest>
+    <item id="c1" href="c1.xhtml" media-type="application/xhtml+xml"/>
+    <item id="c2" href="c2.xhtml" media-type="application/xhtml+xml"/>
+  </manifest>
+  <spine><itemref idref="c1"/><itemref idref="c2"/></spine>
+</package>"#;
+        let c1 = r#"<html><body><p><span epub:type="pagebreak" id="p1" title="1"/>One</p></body></html>"#;
+        let c2 = r#"<html><body><p>One<span role="doc-pagebreak">2</span>Two</p></body></html>"#;
+        let mut zip = zip::ZipWriter::new(Cursor::new(vec![]));
+        let options = zip::write::SimpleFileOptions::default();
+        for (name, contents) in [
+            ("META-INF/container.xml", container),
+            ("package.opf", opf),
+            ("c1.xhtml", c1),
+            ("c2.xhtml", c2),
+        ] {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+        let mut book = Book::from_reader(zip.finish().unwrap(), Limits::default()).unwrap();
+        assert!(book.print_pages().is_empty());
+        let first = book.print_page("1").unwrap();
+        assert_eq!((first.id(), first.fragment()), ("c1", Some("p1")));
+        // the markers are found once, so later lookups don't read the book again
+        book.source = None;
+        let second = book.print_page("2").unwrap();
+        assert_eq!((second.id(), second.fragment()), ("c2", None));
+        assert!(book.print_page("3").is_none());
     }
 }
@@ -0,0 +1,205 @@
+use std::cmp::Reverse;
+
+use quick_xml::{events::Event, Reader};
+use serde::{Deserialize, Serialize};
+
+use super::{encoding::decode, source::Source, EpubError};
+
+const CONTAINER_PATH: &str = "META-INF/container.xml";
+const PACKAGE_MEDIATYPE: &str = "application/oebps-package+xml";
+
+/// How a rendition is laid out, from `rendition:layout`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Layout {
+    Reflowable,
+    PrePaginated,
+}
+impl Layout {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "reflowable" => Some(Self::Reflowable),
+            "pre-paginated" => Some(Self::PrePaginated),
+            _ => None,
+        }
+    }
+}
+
+/// A package document listed in `META-INF/container.xml`, with the rendition selection
+/// attributes that tell it apart from the others
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Rootfile {
+    pub full_path: String,
+    pub media_type: Option<String>,
+    pub language: Option<String>,
+    pub layout: Option<Layout>,
+    pub media: Option<String>,
+    pub accessmode: Option<String>,
+    pub label: Option<String>,
+}
+
+impl Rootfile {
+    /// Whether the rootfile is a package document, rather than say a pdf rendition. Ones
+    /// that don't give a media type are taken to be
+    fn is_package(&self) -> bool {
+        self.media_type.as_deref().unwrap_or(PACKAGE_MEDIATYPE) == PACKAGE_MEDIATYPE
+    }
+}
+
+/// Read the rootfiles from `META-INF/container.xml`
+pub fn read_rootfiles(epub: &mut dyn Source) -> Result<Vec<Rootfile>, EpubError> {
+    let mut buf = vec![];
+    epub.read(CONTAINER_PATH, &mut buf)?;
+    rootfiles(&decode(&buf)?)
+}
+
+/// Every `<rootfile>` in the container document, in order. The first one is the default
+/// rendition
+pub fn rootfiles(xml: &str) -> Result<Vec<Rootfile>, EpubError> {
+    let mut reader = Reader::from_str(xml);
+    let mut rootfiles = vec![];
+    loop {
+        match reader.read_event()? {
+            Event::Empty(ref e) | Event::Start(ref e) if e.local_name().as_ref() == b"rootfile" => {
+                let mut rootfile = Rootfile::default();
+                for attr in e.attributes() {
+                    let attr = attr?;
+                    let value = attr.unescape_value()?.into_owned();
+                    match attr.key.local_name().as_ref() {
+                        b"full-path" => rootfile.full_path = value,
+                        b"media-type" => rootfile.media_type = Some(value),
+                        b"language" => rootfile.language = Some(value),
+                        b"layout" => rootfile.layout = Layout::parse(&value),
+                        b"media" => rootfile.media = Some(value),
+                        b"accessMode" => rootfile.accessmode = Some(value),
+                        b"label" => rootfile.label = Some(value),
+                        _ => (),
+                    }
+                }
+                if rootfile.full_path.is_empty() {
+                    tracing::warn!("ignoring rootfile without a full-path");
+                    continue;
+                }
+                rootfiles.push(rootfile);
+            }
+            Event::Eof => break,
+            _ => (),
+        }
+    }
+    if rootfiles.is_empty() {
+        return Err(EpubError::Rootfile);
+    }
+    Ok(rootfiles)
+}
+
+/// Which rendition to read when a book has more than one
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenditionSelector {
+    /// position of the rootfile in the container, overriding the preferences
+    pub index: Option<usize>,
+    /// preferred language, matching regional variants too
+    pub language: Option<String>,
+    pub layout: Option<Layout>,
+}
+impl RenditionSelector {
+    /// The rootfile that best matches the preferences. Language weighs more than layout,
+    /// renditions that don't say are preferred over ones that don't match, and ties go to
+    /// the earlier one, so without preferences it's the default rendition
+    pub fn select<'a>(&self, rootfiles: &'a [Rootfile]) -> Option<&'a Rootfile> {
+        if let Some(index) = self.index {
+            return rootfiles.get(index).filter(|r| r.is_package());
+        }
+        rootfiles
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| r.is_package())
+            .max_by_key(|(i, r)| {
+                let language = score(&self.language, &r.language, |a, b| {
+                    let (a, b) = (a.to_lowercase(), b.to_lowercase());
+                    a == b || b.starts_with(&format!("{}-", a))
+                });
+                let layout = score(&self.layout, &r.layout, |a, b| a == b);
+                (language, layout, Reverse(*i))
+            })
+            .map(|(_, r)| r)
+    }
+}
+
+/// 2 for a match, 1 when either side has nothing to say, 0 for a mismatch
+fn score<T>(preferred: &Option<T>, actual: &Option<T>, matches: impl Fn(&T, &T) -> bool) -> u8 {
+    match (preferred, actual) {
+        (Some(p), Some(a)) if matches(p, a) => 2,
+        (Some(_), Some(_)) => 0,
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{rootfiles, Layout, RenditionSelector};
+
+    const CONTAINER: &str = r#"<?xml version="1.0"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container"
+    xmlns:rendition="http://www.idpf.org/2013/rendition">
+  <rootfiles>
+    <rootfile full-path="EPUB/reflow/package.opf" media-type="application/oebps-package+xml"
+        rendition:language="en" rendition:layout="reflowable" rendition:label="Text"/>
+    <rootfile full-path="EPUB/fixed/package.opf" media-type="application/oebps-package+xml"
+        rendition:language="en" rendition:layout="pre-paginated" rendition:media="(min-width: 1024px)"/>
+    <rootfile full-path="EPUB/fr/package.opf" media-type="application/oebps-package+xml"
+        rendition:language="fr-CA" rendition:accessMode="textual"/>
+    <rootfile full-path="book.pdf" media-type="application/pdf" rendition:language="de"/>
+  </rootfiles>
+</container>"#;
+
+    #[test]
+    fn all_rootfiles() {
+        let rootfiles = rootfiles(CONTAINER).unwrap();
+        assert_eq!(rootfiles.len(), 4);
+        assert_eq!(rootfiles[0].full_path, "EPUB/reflow/package.opf");
+        assert_eq!(rootfiles[0].layout, Some(Layout::Reflowable));
+        assert_eq!(rootfiles[0].label.as_deref(), Some("Text"));
+        assert_eq!(rootfiles[1].layout, Some(Layout::PrePaginated));
+        assert_eq!(rootfiles[1].media.as_deref(), Some("(min-width: 1024px)"));
+        assert_eq!(rootfiles[2].language.as_deref(), Some("fr-CA"));
+        assert_eq!(rootfiles[2].accessmode.as_deref(), Some("textual"));
+        assert!(super::rootfiles("<container><rootfiles/></container>").is_err());
+    }
+
+    #[test]
+    fn select() {
+        let rootfiles = rootfiles(CONTAINER).unwrap();
+        let path =
+            |selector: RenditionSelector| selector.select(&rootfiles).map(|r| r.full_path.as_str());
+        assert_eq!(path(Default::default()), Some("EPUB/reflow/package.opf"));
+        let fixed = RenditionSelector {
+            layout: Some(Layout::PrePaginated),
+            ..Default::default()
+        };
+        assert_eq!(path(fixed), Some("EPUB/fixed/package.opf"));
+        let french = RenditionSelector {
+            language: Some("FR".to_owned()),
+            layout: Some(Layout::PrePaginated),
+            ..Default::default()
+        };
+        assert_eq!(path(french), Some("EPUB/fr/package.opf"));
+        // only package documents are renditions we can read
+        let german = RenditionSelector {
+            language: Some("de".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(path(german), Some("EPUB/reflow/package.opf"));
+        let explicit = RenditionSelector {
+            index: Some(1),
+            language: Some("fr".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(path(explicit), Some("EPUB/fixed/package.opf"));
+        let pdf = RenditionSelector {
+            index: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(path(pdf), None);
+    }
+}
@@ -0,0 +1,388 @@
+use std::{collections::HashSet, fmt::Display};
+
+use crate::draw::Image;
+
+use super::{
+    book::FONT_MEDIATYPES,
+    container::{read_rootfiles, RenditionSelector},
+    href,
+    html::Node,
+    limits::Limits,
+    manifest::CONTENT_MEDIATYPES,
+    package::Package,
+    source::Source,
+    EpubError,
+};
+
+const EPUB_MIMETYPE: &str = "application/epub+zip";
+const NCX_MEDIATYPE: &str = "application/x-dtbncx+xml";
+const FONT_EXTENSIONS: [&str; 4] = ["otf", "ttf", "woff", "woff2"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// the book can be read, but not everything in it will show
+    Warning,
+    /// the book, or a part of it, can't be read
+    Error,
+}
+
+/// A problem found in a book, and the file it was found in
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub path: Option<String>,
+    pub message: String,
+}
+impl Diagnostic {
+    fn warning(path: Option<&str>, message: String) -> Self {
+        Self {
+            severity: Severity::Warning,
+            path: path.map(ToOwned::to_owned),
+            message,
+        }
+    }
+    pub(super) fn error(path: Option<&str>, message: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            path: path.map(ToOwned::to_owned),
+            message,
+        }
+    }
+}
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        match &self.path {
+            Some(path) => write!(f, "{}: {}: {}", severity, path, self.message),
+            None => write!(f, "{}: {}", severity, self.message),
+        }
+    }
+}
+
+/// Check the container, the package document and the resources it lists, collecting every
+/// problem rather than stopping at the first one
+pub fn diagnose(epub: &mut dyn Source, limits: &Limits) -> Vec<Diagnostic> {
+    let mut report = vec![];
+    let mut buf = vec![];
+
+    match epub.read("mimetype", &mut buf) {
+        Ok(_) if buf.trim_ascii() == EPUB_MIMETYPE.as_bytes() => (),
+        Ok(_) => report.push(Diagnostic::warning(
+            Some("mimetype"),
+            format!("should contain {}", EPUB_MIMETYPE),
+        )),
+        Err(e) => report.push(Diagnostic::warning(Some("mimetype"), e.to_string())),
+    }
+
+    let container = Some("META-INF/container.xml");
+    let rootfiles = match read_rootfiles(epub) {
+        Ok(rootfiles) => rootfiles,
+        Err(e) => {
+            report.push(Diagnostic::error(container, e.to_string()));
+            return report;
+        }
+    };
+    for rootfile in &rootfiles {
+        if let Err(e) = epub.read(&rootfile.full_path, &mut buf) {
+            report.push(Diagnostic::error(container, e.to_string()));
+        }
+    }
+    // the rest of the checks are about the default rendition
+    let Some(rootfile) = RenditionSelector::default().select(&rootfiles) else {
+        report.push(Diagnostic::error(
+            container,
+            "no package document".to_owned(),
+        ));
+        return report;
+    };
+    let package = match Package::read(epub, &rootfile.full_path) {
+        Ok(package) => package,
+        Err(e) => {
+            report.push(Diagnostic::error(
+                Some(&rootfile.full_path),
+                format!("unable to read the package document: {}", e),
+            ));
+            return report;
+        }
+    };
+
+    check_metadata(&package, &mut report);
+    let declared = check_manifest(epub, &package, &mut report);
+    check_spine(epub, &package, limits, &mut report);
+    check_undeclared(epub, &package, &declared, &mut report);
+    report
+}
+
+fn check_metadata(package: &Package, report: &mut Vec<Diagnostic>) {
+    let path = Some(package.path.as_str());
+    let metadata = &package.metadata;
+    if metadata.title().is_none() {
+        report.push(Diagnostic::warning(path, "no title".to_owned()));
+    }
+    if metadata.language().is_none() {
+        report.push(Diagnostic::warning(path, "no language".to_owned()));
+    }
+    match package.unique_identifier.as_deref() {
+        Some(id) if metadata.identifier(id).is_none() => report.push(Diagnostic::error(
+            path,
+            format!("unique identifier {} isn't in the metadata", id),
+        )),
+        Some(_) => (),
+        None => report.push(Diagnostic::error(path, "no unique identifier".to_owned())),
+    }
+}
+
+/// Check that every manifest item can be read, returning the paths of the items
+fn check_manifest(
+    epub: &mut dyn Source,
+    package: &Package,
+    report: &mut Vec<Diagnostic>,
+) -> HashSet<String> {
+    let opf = Some(package.path.as_str());
+    let manifest = &package.manifest;
+    let mut ids = HashSet::new();
+    let mut declared = HashSet::new();
+    let mut buf = vec![];
+
+    if manifest.items().next().is_none() {
+        report.push(Diagnostic::error(opf, "the manifest is empty".to_owned()));
+    }
+    if manifest.item_with_property("nav").is_none()
+        && manifest.item_with_mediatype(NCX_MEDIATYPE).is_none()
+    {
+        report.push(Diagnostic::warning(
+            opf,
+            "no navigation document or NCX".to_owned(),
+        ));
+    }
+
+    for item in manifest.items() {
+        if !ids.insert(item.id()) {
+            report.push(Diagnostic::error(
+                opf,
+                format!("manifest id {} is used more than once", item.id()),
+            ));
+        }
+        let Some(path) = href::resolve_path(&package.path, &item.href) else {
+            report.push(Diagnostic::warning(
+                opf,
+                format!("{} is a remote resource", item.href),
+            ));
+            continue;
+        };
+        match epub.read(&path, &mut buf) {
+            Ok(_) => (),
+            // the path is already in front of the message
+            Err(EpubError::MissingResource(_)) => report.push(Diagnostic::error(
+                Some(&path),
+                "missing from the book".to_owned(),
+            )),
+            Err(e) => report.push(Diagnostic::error(Some(&path), e.to_string())),
+        }
+
+        let mediatype = item.mediatype();
+        if mediatype.starts_with("image/")
+            && manifest
+                .fallback_with_mediatypes(item, &Image::MEDIATYPES)
+                .is_none()
+        {
+            report.push(Diagnostic::warning(
+                Some(&path),
+                format!("unsupported image type {}", mediatype),
+            ));
+        }
+        if has_font_extension(&path) && !FONT_MEDIATYPES.contains(&mediatype) {
+            report.push(Diagnostic::warning(
+                Some(&path),
+                format!("font declared as {} won't be loaded", mediatype),
+            ));
+        }
+        declared.insert(path);
+    }
+    declared
+}
+
+/// Check that every spine item is in the manifest, and that it parses
+fn check_spine(
+    epub: &mut dyn Source,
+    package: &Package,
+    limits: &Limits,
+    report: &mut Vec<Diagnostic>,
+) {
+    let opf = Some(package.path.as_str());
+    let manifest = &package.manifest;
+    let mut buf = vec![];
+
+    if package.spine.items.is_empty() {
+        report.push(Diagnostic::error(opf, "the spine is empty".to_owned()));
+    }
+    for itemref in &package.spine.items {
+        let Some(item) = manifest.item(&itemref.idref) else {
+            report.push(Diagnostic::error(
+                opf,
+                format!("spine item {} isn't in the manifest", itemref.idref),
+            ));
+            continue;
+        };
+        let Some(content) = manifest.fallback_with_mediatypes(item, &CONTENT_MEDIATYPES) else {
+            report.push(Diagnostic::error(
+                opf,
+                format!(
+                    "spine item {} has unsupported type {} and no fallback",
+                    itemref.idref,
+                    item.mediatype()
+                ),
+            ));
+            continue;
+        };
+        let Some(path) = href::resolve_path(&package.path, &content.href) else {
+            continue;
+        };
+        // unreadable files were already reported with the manifest
+        if epub.read(&path, &mut buf).is_err() {
+            continue;
+        }
+        match Node::new(&buf, limits) {
+            Ok(_) => (),
+            Err(EpubError::UnexpectedEof) => {
+                report.push(Diagnostic::error(Some(&path), "no body".to_owned()))
+            }
+            Err(e) => report.push(Diagnostic::error(
+                Some(&path),
+                format!("unable to parse: {}", e),
+            )),
+        }
+    }
+}
+
+/// Warn about files in the container the manifest doesn't list, fonts in particular as
+/// they're then never loaded
+fn check_undeclared(
+    epub: &dyn Source,
+    package: &Package,
+    declared: &HashSet<String>,
+    report: &mut Vec<Diagnostic>,
+) {
+    for path in epub.files() {
+        if declared.contains(&path)
+            || path == package.path
+            || path == "mimetype"
+            || path.starts_with("META-INF/")
+        {
+            continue;
+        }
+        let message = if has_font_extension(&path) {
+            "font isn't declared in the manifest, so it won't be loaded"
+        } else {
+            "not declared in the manifest"
+        };
+        report.push(Diagnostic::warning(Some(&path), message.to_owned()));
+    }
+}
+
+fn has_font_extension(path: &str) -> bool {
+    path.rsplit_once('.')
+        .is_some_and(|(_, ext)| FONT_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::File,
+        io::{Cursor, Write},
+    };
+
+    use crate::epub::{limits::Limits, zip::ZipSource};
+
+    use super::{diagnose, Diagnostic, Severity};
+
+    fn epub(files: &[(&str, &str)]) -> Vec<Diagnostic> {
+        let mut zip = zip::ZipWriter::new(Cursor::new(vec![]));
+        let options = zip::write::SimpleFileOptions::default();
+        for (name, contents) in files {
+            zip.start_file(*name, options).unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+        let data = zip.finish().unwrap();
+        let mut source = ZipSource::new(data, &Limits::default()).unwrap();
+        diagnose(&mut source, &Limits::default())
+    }
+
+    const CONTAINER: &str = r#"<?xml version="1.0"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>"#;
+
+    #[test]
+    fn clean_book() {
+        let file = File::open("testfiles/epubs/pride_and_prejudice.epub").unwrap();
+        let mut source = ZipSource::new(file, &Limits::default()).unwrap();
+        let report = diagnose(&mut source, &Limits::default());
+        assert!(
+            report.iter().all(|d| d.severity == Severity::Warning),
+            "{:?}",
+            report
+        );
+    }
+
+    #[test]
+    fn missing_container() {
+        let report = epub(&[("mimetype", "application/epub+zip")]);
+        assert_eq!(report.len(), 1);
+        assert_eq!(report[0].severity, Severity::Error);
+        assert_eq!(report[0].path.as_deref(), Some("META-INF/container.xml"));
+    }
+
+    #[test]
+    fn broken_book() {
+        let opf = r#"<?xml version="1.0"?>
+<package version="3.0" unique-identifier="uid" xmlns="http://www.idpf.org/2007/opf">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:title>Broken</dc:title>
+    <dc:language>en</dc:language>
+  </metadata>
+  <manifest>
+    <item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+    <item id="ch1" href="Text/ch%201.xhtml" media-type="application/xhtml+xml"/>
+    <item id="ch2" href="Text/ch2.xhtml" media-type="application/xhtml+xml"/>
+    <item id="pdf" href="ch3.pdf" media-type="application/pdf"/>
+    <item id="fig" href="fig.tiff" media-type="image/tiff"/>
+  </manifest>
+  <spine>
+    <itemref idref="ch1"/>
+    <itemref idref="ch2"/>
+    <itemref idref="gone"/>
+    <itemref idref="pdf"/>
+  </spine>
+</package>"#;
+        let deep = format!("<html><body>{}</body></html>", "<div>".repeat(300));
+        let report = epub(&[
+            ("mimetype", "application/epub+zip"),
+            ("META-INF/container.xml", CONTAINER),
+            ("OEBPS/content.opf", opf),
+            ("OEBPS/nav.xhtml", "<html><body><nav/></body></html>"),
+            ("OEBPS/Text/ch 1.xhtml", &deep),
+            ("OEBPS/ch3.pdf", "%PDF"),
+            ("OEBPS/fig.tiff", "II*"),
+            ("OEBPS/Fonts/Serif.OTF", ""),
+        ]);
+        let messages: Vec<_> = report.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            messages,
+            [
+                "error: OEBPS/content.opf: unique identifier uid isn't in the metadata",
+                "error: OEBPS/Text/ch2.xhtml: missing from the book",
+                "warning: OEBPS/fig.tiff: unsupported image type image/tiff",
+                "error: OEBPS/Text/ch 1.xhtml: unable to parse: markup nested deeper than 256 levels",
+                "error: OEBPS/content.opf: spine item gone isn't in the manifest",
+                "error: OEBPS/content.opf: spine item pdf has unsupported type application/pdf and no fallback",
+                "warning: OEBPS/Fonts/Serif.OTF: font isn't declared in the manifest, so it won't be loaded",
+            ]
+        );
+    }
+}
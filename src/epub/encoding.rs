@@ -0,0 +1,91 @@
+use std::borrow::Cow;
+
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+
+use super::EpubError;
+
+/// Decode an xml document to UTF-8, honoring its byte order mark or the encoding given in
+/// its xml declaration. Documents without either are expected to be UTF-8 already
+pub fn decode(bytes: &[u8]) -> Result<Cow<'_, str>, EpubError> {
+    let (encoding, bom_len) = Encoding::for_bom(bytes)
+        .or_else(|| sniff_utf16(bytes).map(|e| (e, 0)))
+        .or_else(|| declared_encoding(bytes).map(|e| (e, 0)))
+        .unwrap_or((UTF_8, 0));
+    let bytes = &bytes[bom_len..];
+
+    if encoding == UTF_8 {
+        return Ok(Cow::Borrowed(std::str::from_utf8(bytes)?));
+    }
+    let (text, malformed) = encoding.decode_without_bom_handling(bytes);
+    if malformed {
+        tracing::warn!("replaced malformed {} sequences", encoding.name());
+    }
+    Ok(text)
+}
+
+/// UTF-16 without a byte order mark, recognised by how `<?` is encoded
+fn sniff_utf16(bytes: &[u8]) -> Option<&'static Encoding> {
+    match bytes.get(..4)? {
+        [b'<', 0, b'?', 0] => Some(UTF_16LE),
+        [0, b'<', 0, b'?'] => Some(UTF_16BE),
+        _ => None,
+    }
+}
+
+/// The encoding named by the `<?xml ... encoding="..."?>` declaration
+fn declared_encoding(bytes: &[u8]) -> Option<&'static Encoding> {
+    let decl = bytes.strip_prefix(b"<?xml")?;
+    let end = decl.windows(2).position(|w| w == b"?>")?;
+    let decl = std::str::from_utf8(&decl[..end]).ok()?;
+
+    let (_, rest) = decl.split_once("encoding")?;
+    let rest = rest.trim_start().strip_prefix('=')?.trim_start();
+    let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let label = rest[1..].split(quote).next()?;
+    let encoding = Encoding::for_label(label.as_bytes());
+    if encoding.is_none() {
+        tracing::warn!("unknown encoding {}, assuming UTF-8", label);
+    }
+    encoding
+}
+
+#[cfg(test)]
+mod tests {
+    use super::decode;
+
+    #[test]
+    fn utf8() {
+        let xml = "<?xml version='1.0' encoding='utf-8'?><p>café</p>";
+        assert_eq!(decode(xml.as_bytes()).unwrap(), xml);
+
+        let with_bom = [&[0xef, 0xbb, 0xbf][..], b"<p>caf\xc3\xa9</p>"].concat();
+        assert_eq!(decode(&with_bom).unwrap(), "<p>café</p>");
+    }
+
+    #[test]
+    fn utf16() {
+        let xml = "<?xml version=\"1.0\"?><p>café</p>";
+        let le: Vec<u8> = xml.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        let with_bom = [&[0xff, 0xfe][..], &le].concat();
+        assert_eq!(decode(&with_bom).unwrap(), xml);
+        assert_eq!(decode(&le).unwrap(), xml);
+
+        let be: Vec<u8> = xml.encode_utf16().flat_map(u16::to_be_bytes).collect();
+        assert_eq!(decode(&be).unwrap(), xml);
+    }
+
+    #[test]
+    fn declared() {
+        let xml = b"<?xml version=\"1.0\" encoding=\"windows-1252\"?><p>caf\xe9 \x93quoted\x94</p>";
+        let decoded = decode(xml).unwrap();
+        assert!(decoded.ends_with("<p>café “quoted”</p>"));
+
+        let latin1 = b"<?xml version='1.0' encoding = 'ISO-8859-1' ?><p>na\xefve</p>";
+        assert!(decode(latin1).unwrap().ends_with("<p>naïve</p>"));
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        assert!(decode(b"<p>caf\xe9</p>").is_err());
+    }
+}
@@ -0,0 +1,206 @@
+use quick_xml::{events::Event, Reader};
+
+use super::EpubError;
+
+const IDPF_ALGORITHM: &str = "http://www.idpf.org/2008/embedding";
+const ADOBE_ALGORITHM: &str = "http://ns.adobe.com/pdf/enc#RC";
+/// How many leading bytes of a resource each algorithm obfuscates
+const IDPF_LENGTH: usize = 1040;
+const ADOBE_LENGTH: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Algorithm {
+    /// IDPF font obfuscation, keyed by the SHA-1 of the unique identifier
+    Idpf,
+    /// Adobe font obfuscation, keyed by the book's UUID
+    Adobe,
+    /// Actual encryption, which we can't undo
+    Unsupported,
+}
+impl From<&str> for Algorithm {
+    fn from(value: &str) -> Self {
+        match value {
+            IDPF_ALGORITHM => Self::Idpf,
+            ADOBE_ALGORITHM => Self::Adobe,
+            _ => Self::Unsupported,
+        }
+    }
+}
+
+/// The resources listed in `META-INF/encryption.xml`, and the keys to deobfuscate them
+#[derive(Debug, Default)]
+pub struct Encryption {
+    resources: Vec<(String, Algorithm)>,
+    idpf_key: Option<[u8; 20]>,
+    adobe_key: Option<[u8; 16]>,
+}
+impl Encryption {
+    pub fn from_xml(xml: &str) -> Result<Self, EpubError> {
+        let mut reader = Reader::from_str(xml);
+        let mut encryption = Self::default();
+        let mut algorithm = None;
+        let mut uri = None;
+        loop {
+            match reader.read_event() {
+                Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) => {
+                    match e.local_name().as_ref() {
+                        b"EncryptionMethod" => {
+                            if let Some(attr) = e.try_get_attribute("Algorithm")? {
+                                algorithm = Some(Algorithm::from(attr.unescape_value()?.as_ref()));
+                            }
+                        }
+                        b"CipherReference" => {
+                            if let Some(attr) = e.try_get_attribute("URI")? {
+                                uri = Some(attr.unescape_value()?.into_owned());
+                            }
+                        }
+                        _ => (),
+                    }
+                }
+                Ok(Event::End(ref e)) if e.local_name().as_ref() == b"EncryptedData" => {
+                    if let (Some(algorithm), Some(uri)) = (algorithm.take(), uri.take()) {
+                        encryption.resources.push((uri, algorithm));
+                    }
+                }
+                Ok(Event::Eof) => break,
+                Err(e) => return Err(e.into()),
+                _ => (),
+            }
+        }
+        Ok(encryption)
+    }
+
+    /// Derive the keys from the package's unique identifier and the book's UUID
+    pub fn with_keys(mut self, unique_identifier: Option<&str>, uuid: Option<&str>) -> Self {
+        self.idpf_key = unique_identifier.map(|id| {
+            // whitespace isn't part of the key
+            let id: String = id
+                .chars()
+                .filter(|c| !matches!(c, ' ' | '\t' | '\r' | '\n'))
+                .collect();
+            sha1_smol::Sha1::from(id).digest().bytes()
+        });
+        self.adobe_key = uuid.and_then(|uuid| {
+            let hex: String = uuid
+                .trim_start_matches("urn:uuid:")
+                .chars()
+                .filter(|c| *c != '-')
+                .collect();
+            let bytes = u128::from_str_radix(&hex, 16).ok()?;
+            Some(bytes.to_be_bytes())
+        });
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.resources.is_empty()
+    }
+
+    /// Undo the obfuscation of a resource in place. Resources that aren't obfuscated are left
+    /// as they are
+    pub fn deobfuscate(&self, path: &str, data: &mut [u8]) {
+        let Some((_, algorithm)) = self.resources.iter().find(|(p, _)| p == path) else {
+            return;
+        };
+        let (key, length): (&[u8], _) = match (algorithm, &self.idpf_key, &self.adobe_key) {
+            (Algorithm::Idpf, Some(key), _) => (key, IDPF_LENGTH),
+            (Algorithm::Adobe, _, Some(key)) => (key, ADOBE_LENGTH),
+            (Algorithm::Unsupported, _, _) => {
+                tracing::warn!("{} is encrypted", path);
+                return;
+            }
+            _ => {
+                tracing::warn!("no key to deobfuscate {}", path);
+                return;
+            }
+        };
+        data.iter_mut()
+            .take(length)
+            .zip(key.iter().cycle())
+            .for_each(|(b, k)| *b ^= k);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Algorithm, Encryption};
+
+    const XML: &str = r#"<?xml version="1.0"?>
+<encryption xmlns="urn:oasis:names:tc:opendocument:xmlns:container"
+    xmlns:enc="http://www.w3.org/2001/04/xmlenc#">
+  <enc:EncryptedData>
+    <enc:EncryptionMethod Algorithm="http://www.idpf.org/2008/embedding"/>
+    <enc:CipherData>
+      <enc:CipherReference URI="OEBPS/fonts/idpf.otf"/>
+    </enc:CipherData>
+  </enc:EncryptedData>
+  <enc:EncryptedData>
+    <enc:EncryptionMethod Algorithm="http://ns.adobe.com/pdf/enc#RC"/>
+    <enc:CipherData>
+      <enc:CipherReference URI="OEBPS/fonts/adobe.ttf"/>
+    </enc:CipherData>
+  </enc:EncryptedData>
+  <enc:EncryptedData>
+    <enc:EncryptionMethod Algorithm="http://www.w3.org/2001/04/xmlenc#aes128-cbc"/>
+    <enc:CipherData>
+      <enc:CipherReference URI="OEBPS/chapter.xhtml"/>
+    </enc:CipherData>
+  </enc:EncryptedData>
+</encryption>"#;
+
+    #[test]
+    fn resources() {
+        let encryption = Encryption::from_xml(XML).unwrap();
+        let algorithms: Vec<_> = encryption.resources.iter().map(|(_, a)| *a).collect();
+        assert_eq!(
+            algorithms,
+            [Algorithm::Idpf, Algorithm::Adobe, Algorithm::Unsupported]
+        );
+        assert_eq!(encryption.resources[0].0, "OEBPS/fonts/idpf.otf");
+    }
+
+    #[test]
+    fn idpf() {
+        // sha1("abc"), once the whitespace is removed from the identifier
+        let key = [
+            0xa9, 0x99, 0x3e, 0x36, 0x47, 0x06, 0x81, 0x6a, 0xba, 0x3e, 0x25, 0x71, 0x78, 0x50,
+            0xc2, 0x6c, 0x9c, 0xd0, 0xd8, 0x9d,
+        ];
+        let encryption = Encryption::from_xml(XML)
+            .unwrap()
+            .with_keys(Some(" a b\tc\n"), None);
+        let mut data = vec![0; 1100];
+        encryption.deobfuscate("OEBPS/fonts/idpf.otf", &mut data);
+        assert_eq!(data[..20], key);
+        assert_eq!(data[1020..1040], key);
+        assert!(data[1040..].iter().all(|b| *b == 0));
+
+        // applying it again restores the original
+        encryption.deobfuscate("OEBPS/fonts/idpf.otf", &mut data);
+        assert!(data.iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn adobe() {
+        let encryption = Encryption::from_xml(XML)
+            .unwrap()
+            .with_keys(None, Some("urn:uuid:00112233-4455-6677-8899-aabbccddeeff"));
+        let mut data = vec![0; 1100];
+        encryption.deobfuscate("OEBPS/fonts/adobe.ttf", &mut data);
+        assert_eq!(data[..4], [0x00, 0x11, 0x22, 0x33]);
+        assert_eq!(data[1008..1024], data[..16]);
+        assert!(data[1024..].iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn untouched() {
+        let encryption = Encryption::from_xml(XML)
+            .unwrap()
+            .with_keys(Some("abc"), None);
+        let mut data = vec![0; 100];
+        encryption.deobfuscate("OEBPS/fonts/adobe.ttf", &mut data);
+        encryption.deobfuscate("OEBPS/chapter.xhtml", &mut data);
+        encryption.deobfuscate("OEBPS/other.otf", &mut data);
+        assert!(data.iter().all(|b| *b == 0));
+    }
+}
@@ -11,8 +11,23 @@ pub enum EpubError {
     #[error("string")]
     Utf8(#[from] std::str::Utf8Error),
 
-    #[error("requested content not found: {0}")]
-    ContentNotFound(String),
+    #[error("resource missing from the book: {0}")]
+    MissingResource(String),
+
+    #[error("markup nested deeper than {0} levels")]
+    TooDeep(usize),
+
+    #[error("{0} is larger than {1} bytes")]
+    EntryTooLarge(String, u64),
+
+    #[error("book decompresses to more than {0} bytes")]
+    TooLarge(u64),
+
+    #[error("book has more than {0} files")]
+    TooManyEntries(usize),
+
+    #[error("file name leaves the container: {0}")]
+    PathTraversal(String),
 
     #[error("xml")]
     Xml(#[from] quick_xml::Error),
@@ -25,4 +40,7 @@ pub enum EpubError {
 
     #[error("xml file ended prematurely")]
     UnexpectedEof,
+
+    #[error("image")]
+    Image(#[from] crate::draw::Error),
 }
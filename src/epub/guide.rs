@@ -0,0 +1,94 @@
+use quick_xml::{events::Event, Reader};
+
+use super::EpubError;
+
+#[derive(Debug, Default)]
+pub struct Guide {
+    references: Vec<Reference>,
+}
+impl Guide {
+    pub fn extract(reader: &mut Reader<&[u8]>) -> Result<Self, EpubError> {
+        let mut depth = 1;
+        let mut guide = Self::default();
+        loop {
+            match reader.read_event() {
+                Ok(Event::Empty(ref e)) if e.local_name().as_ref() == b"reference" => {
+                    let mut reference = Reference::default();
+                    for attr in e.attributes() {
+                        let attr = attr?;
+                        let val = attr.unescape_value()?.into_owned();
+                        match attr.key.as_ref() {
+                            b"type" => reference.kind = val.to_lowercase(),
+                            b"title" => reference.title = val,
+                            b"href" => reference.href = val,
+                            _ => (),
+                        }
+                    }
+                    guide.references.push(reference);
+                }
+                Ok(Event::Start(_)) => depth += 1,
+                Ok(Event::End(_)) => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                Ok(Event::Eof) => return Err(EpubError::UnexpectedEof),
+                Err(e) => return Err(e.into()),
+                _ => {}
+            }
+        }
+        Ok(guide)
+    }
+
+    /// The first reference of the given type, e.g. `cover` or `text`
+    pub fn reference(&self, kind: &str) -> Option<&Reference> {
+        self.references.iter().find(|r| r.kind == kind)
+    }
+
+    pub fn references(&self) -> &[Reference] {
+        &self.references
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Reference {
+    pub kind: String,
+    pub title: String,
+    pub href: String,
+}
+
+#[cfg(test)]
+mod tests {
+
+    use quick_xml::events::Event;
+    use quick_xml::Reader;
+
+    use super::Guide;
+
+    #[test]
+    fn happy_path() {
+        let xml = r#"
+<?xml version='1.0' encoding='utf-8'?>
+  <guide>
+    <reference type="cover" title="Cover" href="titlepage.xhtml"/>
+    <reference type="toc" title="Table of Contents" href="text/part0001.html#toc"/>
+    <reference type="Text" title="Beginning" href="text/part0002.html"/>
+  </guide>
+        "#;
+        let mut reader = Reader::from_str(xml);
+        loop {
+            match reader.read_event() {
+                Ok(Event::Start(ref e)) if e.name().as_ref() == b"guide" => {
+                    let result = Guide::extract(&mut reader).unwrap();
+                    assert_eq!(result.references.len(), 3);
+                    assert_eq!(result.reference("cover").unwrap().href, "titlepage.xhtml");
+                    assert_eq!(result.reference("text").unwrap().title, "Beginning");
+                    assert!(result.reference("bodymatter").is_none());
+                }
+                Ok(Event::Eof) => break, // exits the loop when reaching end of file
+                _ => (),
+            }
+        }
+    }
+}
@@ -0,0 +1,163 @@
+/// Where an href found in the book points: a path inside the container and the fragment, if
+/// there is one
+#[derive(Debug, Clone, PartialEq)]
+pub struct Href {
+    pub path: String,
+    pub fragment: Option<String>,
+}
+
+/// Resolve `href`, found in the document at `from`, to a path inside the container. Both are
+/// percent-decoded, `.` and `..` segments are normalized and query strings are dropped.
+/// Links with a scheme point outside the book and resolve to nothing
+pub fn resolve(from: &str, href: &str) -> Option<Href> {
+    if has_scheme(href) {
+        return None;
+    }
+    let (href, fragment) = match href.split_once('#') {
+        Some((h, f)) => (h, Some(percent_decode(f)).filter(|f| !f.is_empty())),
+        None => (href, None),
+    };
+    let href = href.split('?').next().unwrap_or_default();
+
+    let path = if href.is_empty() {
+        from.to_owned()
+    } else if let Some(absolute) = href.strip_prefix('/') {
+        normalize("", absolute)
+    } else {
+        let dir = from.rsplit_once('/').map_or("", |(dir, _)| dir);
+        normalize(dir, href)
+    };
+    Some(Href { path, fragment })
+}
+
+/// Resolve an href to a path, dropping the fragment
+pub fn resolve_path(from: &str, href: &str) -> Option<String> {
+    resolve(from, href).map(|h| h.path)
+}
+
+/// Join `href` to the directory `dir`, lexically resolving `.` and `..` segments. Each
+/// segment of the href is percent-decoded after splitting, so an escaped `/` stays part of
+/// its segment. Going above the root stays at the root
+fn normalize(dir: &str, href: &str) -> String {
+    let mut segments: Vec<String> = dir
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .map(ToOwned::to_owned)
+        .collect();
+    for segment in href.split('/') {
+        match percent_decode(segment) {
+            s if s.is_empty() || s == "." => (),
+            s if s == ".." => {
+                segments.pop();
+            }
+            s => segments.push(s),
+        }
+    }
+    segments.join("/")
+}
+
+/// Whether the href starts with a URL scheme such as `https:` or `mailto:`
+fn has_scheme(href: &str) -> bool {
+    let Some((scheme, _)) = href.split_once(':') else {
+        return false;
+    };
+    let mut chars = scheme.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+}
+
+/// Decode `%XX` escapes. Invalid escapes are kept as they are
+fn percent_decode(s: &str) -> String {
+    if !s.contains('%') {
+        return s.to_owned();
+    }
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(b) => {
+                decoded.push(b);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{resolve, Href};
+
+    fn href(path: &str, fragment: Option<&str>) -> Option<Href> {
+        Some(Href {
+            path: path.to_owned(),
+            fragment: fragment.map(ToOwned::to_owned),
+        })
+    }
+
+    #[test]
+    fn relative() {
+        let from = "OEBPS/Text/ch1.xhtml";
+        assert_eq!(
+            resolve(from, "ch2.xhtml"),
+            href("OEBPS/Text/ch2.xhtml", None)
+        );
+        assert_eq!(
+            resolve(from, "../Images/fig.png"),
+            href("OEBPS/Images/fig.png", None)
+        );
+        assert_eq!(
+            resolve(from, "./../Text/./ch2.xhtml#p3"),
+            href("OEBPS/Text/ch2.xhtml", Some("p3"))
+        );
+        assert_eq!(resolve(from, "#p3"), href(from, Some("p3")));
+        assert_eq!(resolve(from, "../../../x.png"), href("x.png", None));
+    }
+
+    #[test]
+    fn package_at_root() {
+        assert_eq!(resolve("content.opf", "ch1.xhtml"), href("ch1.xhtml", None));
+        assert_eq!(
+            resolve("ch1.xhtml", "/images/a.jpg"),
+            href("images/a.jpg", None)
+        );
+    }
+
+    #[test]
+    fn percent_encoded() {
+        assert_eq!(
+            resolve("OEBPS/content.opf", "Text/Chapter%201.xhtml?x=1#caf%C3%A9"),
+            href("OEBPS/Text/Chapter 1.xhtml", Some("café"))
+        );
+        assert_eq!(resolve("a.xhtml", "100%.png"), href("100%.png", None));
+        assert_eq!(
+            resolve("OEBPS/content.opf", "/OEBPS/Chapter%201.xhtml"),
+            href("OEBPS/Chapter 1.xhtml", None)
+        );
+        // an escaped slash is part of the name rather than a separator
+        assert_eq!(
+            resolve("OEBPS/content.opf", "a%2F..%2Fb.xhtml"),
+            href("OEBPS/a/../b.xhtml", None)
+        );
+        assert_eq!(
+            resolve("OEBPS/content.opf", "%2E%2E/x.png"),
+            href("x.png", None)
+        );
+    }
+
+    #[test]
+    fn external() {
+        assert_eq!(resolve("a.xhtml", "https://example.com/#x"), None);
+        assert_eq!(resolve("a.xhtml", "mailto:someone@example.com"), None);
+    }
+}
@@ -1,15 +1,17 @@
-use std::str::FromStr;
+use std::{borrow::Cow, str::FromStr};
 
 use quick_xml::{
+    escape::resolve_html5_entity,
     events::{attributes::Attribute as QAttribute, BytesStart, Event},
     Reader,
 };
 
-use super::EpubError;
+use super::{encoding::decode, limits::Limits, EpubError};
 
 #[derive(Default, Debug, PartialEq, Clone, Copy)]
 pub enum ElementVariant {
     A,
+    Aside,
     B,
     Blockquote,
     Body,
@@ -49,8 +51,9 @@ impl FromStr for ElementVariant {
             "br" => Ok(Self::Br),
             "hr" => Ok(Self::Hr),
             "a" => Ok(Self::A),
+            "aside" => Ok(Self::Aside),
             "tr" => Ok(Self::Tr),
-            "image" => Ok(Self::Image),
+            "image" | "img" => Ok(Self::Image),
             _ => Ok(Self::Ignored),
         }
     }
@@ -65,7 +68,7 @@ impl Attribute {
     fn parse(attr: QAttribute<'_>) -> Result<Self, EpubError> {
         let key = attr.key.into_inner();
         let key = std::str::from_utf8(key)?.to_owned();
-        let value = attr.unescape_value()?.into_owned();
+        let value = unescape(std::str::from_utf8(&attr.value)?).into_owned();
         Ok(Self { key, value })
     }
     pub fn value(&self) -> &str {
@@ -73,8 +76,45 @@ impl Attribute {
     }
 }
 
+/// Elements that never have content, so may appear without being closed
+const VOID_ELEMENTS: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// Elements whose start implicitly closes an open `<p>`
+const BLOCK_ELEMENTS: [&str; 25] = [
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "details",
+    "div",
+    "dl",
+    "fieldset",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+];
+
 #[derive(Default, Debug, Clone)]
 pub struct Element {
+    // lowercase local name, to match end tags against
+    name: String,
     variant: ElementVariant,
     attributes: Vec<Attribute>,
     children: Vec<Node>,
@@ -84,28 +124,124 @@ impl Element {
         let local_name = tag.name().local_name();
         let parsed_name = std::str::from_utf8(local_name.into_inner())?;
         let variant = parsed_name.parse()?;
+        let name = parsed_name.to_lowercase();
 
         let mut attributes = vec![];
-        for attr in tag.attributes().filter(|a| a.is_ok()) {
-            let attr = attr.map(|a| Attribute::parse(a).unwrap())?;
-            attributes.push(attr);
+        for attr in tag.attributes().flatten() {
+            attributes.push(Attribute::parse(attr)?);
         }
 
         Ok(Self {
+            name,
             variant,
             attributes,
             children: vec![],
         })
     }
+    /// An element made up rather than parsed, for formats whose markup is mapped onto ours
+    pub fn build(
+        name: &str,
+        variant: ElementVariant,
+        attributes: Vec<(String, String)>,
+        children: Vec<Node>,
+    ) -> Self {
+        Self {
+            name: name.to_lowercase(),
+            variant,
+            attributes: attributes
+                .into_iter()
+                .map(|(key, value)| Attribute { key, value })
+                .collect(),
+            children,
+        }
+    }
+    /// The lowercase local name of the tag
+    pub fn name(&self) -> &str {
+        &self.name
+    }
     pub fn variant(&self) -> ElementVariant {
         self.variant
     }
+    fn is_void(&self) -> bool {
+        VOID_ELEMENTS.contains(&self.name.as_str())
+    }
+    /// Whether starting `next` inside this element closes it first, as in `<p>one<p>two`
+    fn closed_by(&self, next: &Element) -> bool {
+        let next = next.name.as_str();
+        match self.name.as_str() {
+            "p" => BLOCK_ELEMENTS.contains(&next),
+            "li" => next == "li",
+            "dt" | "dd" => matches!(next, "dt" | "dd"),
+            "tr" => next == "tr",
+            "td" | "th" => matches!(next, "td" | "th" | "tr"),
+            _ => false,
+        }
+    }
     pub fn attribute(&self, key: &str) -> Option<&Attribute> {
         self.attributes.iter().find(|a| a.key == key)
     }
+    pub fn id(&self) -> Option<&str> {
+        self.attribute("id").map(|a| a.value())
+    }
     pub fn children(&self) -> &[Node] {
         &self.children
     }
+    /// Whether the element has the given structural semantic, either as an `epub:type`
+    /// or as the equivalent `doc-` aria role
+    pub fn has_semantic(&self, kind: &str) -> bool {
+        let epub_type = self.attribute("epub:type").map(|a| a.value());
+        let role = self.attribute("role").map(|a| a.value());
+        epub_type
+            .into_iter()
+            .flat_map(str::split_whitespace)
+            .any(|t| t == kind)
+            || role
+                .into_iter()
+                .flat_map(str::split_whitespace)
+                .any(|r| r.strip_prefix("doc-") == Some(kind))
+    }
+    /// Whether this is a link to a footnote or endnote
+    pub fn is_noteref(&self) -> bool {
+        self.variant == ElementVariant::A && self.has_semantic("noteref")
+    }
+    /// Whether this is a footnote or endnote that shouldn't be shown in the text flow. Notes
+    /// are often list items or divs rather than asides, so any element can be one
+    pub fn is_note(&self) -> bool {
+        [
+            "footnote",
+            "footnotes",
+            "endnote",
+            "endnotes",
+            "rearnote",
+            "rearnotes",
+            "note",
+        ]
+        .iter()
+        .any(|k| self.has_semantic(k))
+    }
+    /// The print page number of a page break marker: its title, its aria-label, or the
+    /// text it wraps
+    pub fn page_break(&self) -> Option<String> {
+        if !self.has_semantic("pagebreak") {
+            return None;
+        }
+        let label = match ["title", "aria-label"]
+            .iter()
+            .find_map(|k| self.attribute(k))
+        {
+            Some(attr) => attr.value().to_owned(),
+            None => self.children.iter().filter_map(Node::text).collect(),
+        };
+        let label = label.trim();
+        (!label.is_empty()).then(|| label.to_owned())
+    }
+    /// The target of an `<img>` or svg `<image>` element
+    pub fn image_href(&self) -> Option<&str> {
+        ["xlink:href", "href", "src"]
+            .iter()
+            .find_map(|k| self.attribute(k))
+            .map(|a| a.value())
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -115,13 +251,21 @@ pub enum Node {
 }
 
 impl Node {
-    pub fn new(input: &[u8]) -> Result<Self, EpubError> {
-        let t = std::str::from_utf8(input)?;
-        let mut reader = quick_xml::Reader::from_str(t);
+    /// Parse the body of a content document, failing if its elements nest deeper than the
+    /// limits allow
+    pub fn new(input: &[u8], limits: &Limits) -> Result<Self, EpubError> {
+        let t = decode(input)?;
+        let mut reader = quick_xml::Reader::from_str(&t);
+        // mismatched end tags are repaired by `extract`
+        reader.config_mut().check_end_names = false;
+        reader.config_mut().allow_unmatched_ends = true;
         loop {
             match reader.read_event() {
-                Ok(Event::Start(ref e)) if e.name().as_ref() == b"body" => {
-                    return extract(e, &mut reader);
+                Ok(Event::Start(ref e)) if is_body(e) => {
+                    return extract(e, &mut reader, limits.max_depth);
+                }
+                Ok(Event::Empty(ref e)) if is_body(e) => {
+                    return Ok(Node::Element(Element::new(e)?));
                 }
                 Ok(Event::Eof) => return Err(EpubError::UnexpectedEof),
                 Err(e) => return Err(e.into()),
@@ -141,101 +285,203 @@ impl Node {
         }
         None
     }
+    /// Find the element with the given `id` attribute
+    pub fn find_by_id(&self, id: &str) -> Option<&Node> {
+        self.iter()
+            .find(|n| n.element().is_some_and(|e| e.id() == Some(id)))
+    }
+    /// The first image in the tree, along with whether the tree contains any text
+    pub fn first_image(&self) -> Option<(&str, bool)> {
+        let href = self
+            .iter()
+            .filter_map(|n| n.element())
+            .filter(|e| e.variant() == ElementVariant::Image)
+            .find_map(|e| e.image_href())?;
+        let has_text = self.iter().any(|n| matches!(n, Node::Text(_)));
+        Some((href, has_text))
+    }
     pub fn iter<'a>(&'a self) -> NodeIterator<'a> {
         NodeIterator {
-            stack: vec![(0, self)],
+            stack: vec![self],
+            descended: 0,
         }
     }
 }
 
+/// Depth-first iterator over a node and all of its descendants
 pub struct NodeIterator<'a> {
-    stack: Vec<(usize, &'a Node)>,
+    // nodes still to visit, next one last
+    stack: Vec<&'a Node>,
+    // how many children the last returned node added to the stack
+    descended: usize,
+}
+impl NodeIterator<'_> {
+    /// Don't visit the descendants of the last returned node
+    pub fn skip_children(&mut self) {
+        let len = self.stack.len() - self.descended;
+        self.stack.truncate(len);
+        self.descended = 0;
+    }
 }
 impl<'a> Iterator for NodeIterator<'a> {
     type Item = &'a Node;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let (next_child, current_node) = self.stack.pop()?;
-
-        // if it's a branch
-        if let Node::Element(el) = current_node {
-            // if it has more children then we're done
-            if let Some(child_node) = el.children().get(next_child) {
-                // push the parent back to the stack
-                self.stack.push((next_child + 1, current_node));
-                // then push the new child to the stack
-                self.stack.push((0, child_node));
-
-                return Some(current_node);
-            }
+        let node = self.stack.pop()?;
+        self.descended = 0;
+        if let Node::Element(el) = node {
+            self.stack.extend(el.children().iter().rev());
+            self.descended = el.children().len();
         }
+        Some(node)
+    }
+}
 
-        // if we're here there were no more children on this branch nd we need to find a sibling
-        loop {
-            // pop parent again to try to find the next child
-            let Some((next_child, parent_node)) = self.stack.pop() else {
-                break;
-            };
-
-            // get the inner element
-            let Node::Element(el) = parent_node else {
-                panic!("parent node cannot be a leaf");
-            };
-
-            // if it has more children then we're done
-            if let Some(child_node) = el.children().get(next_child) {
-                // push the parent back to the stack
-                self.stack.push((next_child + 1, parent_node));
-                // then push the new child to the stack
-                self.stack.push((0, child_node));
-                break;
-            }
-        }
+/// `<body>`, whatever namespace prefix it's written with
+fn is_body(tag: &BytesStart) -> bool {
+    tag.local_name().as_ref().eq_ignore_ascii_case(b"body")
+}
 
-        Some(current_node)
+/// Move the innermost open element into its parent
+fn close(stack: &mut Vec<Element>) {
+    if let Some(el) = stack.pop() {
+        if let Some(parent) = stack.last_mut() {
+            parent.children.push(Node::Element(el));
+        }
     }
 }
 
-pub fn extract<'a>(tag: &BytesStart, reader: &mut Reader<&[u8]>) -> Result<Node, EpubError> {
-    let mut node = Element::new(tag)?;
+/// Build the tree for the element that has just started. Malformed markup is repaired the
+/// way html parsers would rather than rejected: unclosed and void elements are closed
+/// implicitly, stray end tags are dropped and the document may end early. Nesting deeper
+/// than `max_depth` is an error
+pub fn extract(
+    tag: &BytesStart,
+    reader: &mut Reader<&[u8]>,
+    max_depth: usize,
+) -> Result<Node, EpubError> {
+    let mut stack = vec![Element::new(tag)?];
 
-    for attr in tag.attributes().filter(|a| a.is_ok()) {
-        let attr = attr.map(|a| Attribute::parse(a).unwrap())?;
-        node.attributes.push(attr);
-    }
     loop {
+        let top = stack.len() - 1;
         match reader.read_event() {
             Ok(Event::Start(ref e)) => {
-                let child = extract(e, reader)?;
-                node.children.push(child);
+                let el = Element::new(e)?;
+                while stack.len() > 1 && stack[stack.len() - 1].closed_by(&el) {
+                    tracing::warn!(
+                        "closing <{}> before <{}>",
+                        stack[stack.len() - 1].name,
+                        el.name
+                    );
+                    close(&mut stack);
+                }
+                if el.is_void() {
+                    let top = stack.len() - 1;
+                    stack[top].children.push(Node::Element(el));
+                } else if stack.len() >= max_depth {
+                    return Err(EpubError::TooDeep(max_depth));
+                } else {
+                    stack.push(el);
+                }
             }
             Ok(Event::Text(text)) => {
-                let t = std::str::from_utf8(&text)?;
-                let result: String = t.split_whitespace().collect::<Vec<_>>().join(" ");
+                let t = unescape(std::str::from_utf8(&text)?);
+                // only xml whitespace collapses, so `&nbsp;` survives
+                let result: String = t.split_ascii_whitespace().collect::<Vec<_>>().join(" ");
 
                 if !result.is_empty() {
-                    node.children.push(Node::Text(result));
+                    stack[top].children.push(Node::Text(result));
                 }
             }
             Ok(Event::Empty(ref e)) => {
                 let cur = Element::new(e)?;
-                node.children.push(Node::Element(cur));
+                stack[top].children.push(Node::Element(cur));
+            }
+            Ok(Event::End(ref e)) => {
+                let name = std::str::from_utf8(e.local_name().into_inner())?.to_lowercase();
+                let Some(open) = stack.iter().rposition(|el| el.name == name) else {
+                    if !VOID_ELEMENTS.contains(&name.as_str()) {
+                        tracing::warn!("ignoring stray </{}>", name);
+                    }
+                    continue;
+                };
+                for el in &stack[open + 1..] {
+                    tracing::warn!("closing unclosed <{}> at </{}>", el.name, name);
+                }
+                while stack.len() > open + 1 {
+                    close(&mut stack);
+                }
+                if open == 0 {
+                    break;
+                }
+                close(&mut stack);
             }
-            Ok(Event::End(_)) => {
-                return Ok(Node::Element(node));
+            Ok(Event::Eof) => {
+                tracing::warn!("document ended inside <{}>", stack[top].name);
+                break;
             }
-            Ok(Event::Eof) => return Err(EpubError::UnexpectedEof),
             Err(e) => {
-                return Err(e.into());
+                tracing::warn!("giving up parsing at {}: {}", reader.buffer_position(), e);
+                break;
             }
             _ => {}
         }
     }
+
+    while stack.len() > 1 {
+        close(&mut stack);
+    }
+    Ok(Node::Element(stack.remove(0)))
+}
+
+/// Resolve character references and HTML5 named entities. Anything that doesn't resolve is
+/// kept as written rather than failing the whole document
+pub(super) fn unescape(raw: &str) -> Cow<'_, str> {
+    if !raw.contains('&') {
+        return Cow::Borrowed(raw);
+    }
+    let mut unescaped = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let resolved = rest
+            .find(';')
+            .and_then(|end| Some((resolve_entity(&rest[1..end])?, end)));
+        match resolved {
+            Some((c, end)) => {
+                unescaped.push_str(&c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                tracing::warn!("unknown entity in {:?}", raw);
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    Cow::Owned(unescaped)
+}
+
+fn resolve_entity(entity: &str) -> Option<Cow<'static, str>> {
+    let Some(number) = entity.strip_prefix('#') else {
+        return resolve_html5_entity(entity).map(Cow::Borrowed);
+    };
+    let code = match number.strip_prefix(['x', 'X']) {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => number.parse(),
+    };
+    let c = char::from_u32(code.ok()?)?;
+    Some(Cow::Owned(c.to_string()))
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::epub::html::Node;
+    use crate::epub::{
+        html::{unescape, ElementVariant, Node},
+        EpubError, Limits,
+    };
 
     #[test]
     fn full_xhtml() {
@@ -252,8 +498,8 @@ mod tests {
                 </body>
             </html>
         "#;
-        let node = Node::new(xml.as_bytes()).unwrap();
-        let mut node_iter = node.iter().skip(5);
+        let node = Node::new(xml.as_bytes(), &Limits::default()).unwrap();
+        let mut node_iter = node.iter().skip(4);
 
         let elem = node_iter.next().unwrap();
         assert_eq!(
@@ -266,4 +512,174 @@ mod tests {
             "I must trouble you"
         );
     }
+
+    #[test]
+    fn find_by_id() {
+        let xml = r#"
+            <html xmlns="http://www.w3.org/1999/xhtml">
+                <body>
+                    <h2 id="ch1">Chapter 1</h2>
+                    <p>Text</p>
+                    <div><h2 id="ch2">Chapter 2</h2></div>
+                </body>
+            </html>
+        "#;
+        let node = Node::new(xml.as_bytes(), &Limits::default()).unwrap();
+        let elem = node.find_by_id("ch2").unwrap().element().unwrap();
+        assert_eq!(elem.children()[0].text(), Some("Chapter 2"));
+        assert!(node.find_by_id("ch3").is_none());
+    }
+
+    #[test]
+    fn footnotes() {
+        let xml = r##"
+            <html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+                <body>
+                    <p>Text<a epub:type="noteref" href="#n1">1</a> more</p>
+                    <aside epub:type="footnote" id="n1"><p>The note</p></aside>
+                    <p role="doc-noteref">After</p>
+                </body>
+            </html>
+        "##;
+        let node = Node::new(xml.as_bytes(), &Limits::default()).unwrap();
+        let noterefs = node
+            .iter()
+            .filter_map(|n| n.element())
+            .filter(|e| e.is_noteref())
+            .count();
+        assert_eq!(noterefs, 1);
+        assert!(node.find_by_id("n1").unwrap().element().unwrap().is_note());
+
+        // skipping the note leaves only the main flow
+        let mut nodes = node.iter();
+        let mut text = vec![];
+        while let Some(n) = nodes.next() {
+            if n.element().is_some_and(|e| e.is_note()) {
+                nodes.skip_children();
+            }
+            text.extend(n.text());
+        }
+        assert_eq!(text, ["Text", "1", "more", "After"]);
+    }
+
+    #[test]
+    fn notes_without_aside() {
+        let xml = r##"
+            <html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+                <body>
+                    <p>Text</p>
+                    <ol>
+                        <li epub:type="endnote" id="n1"><p>The first</p></li>
+                        <li role="doc-endnote" id="n2"><p>The second</p></li>
+                    </ol>
+                    <div epub:type="footnote" id="n3"><p>A div</p></div>
+                    <section role="doc-endnotes" id="notes"><p>All of them</p></section>
+                    <div id="plain"><p>Not a note</p></div>
+                </body>
+            </html>
+        "##;
+        let node = Node::new(xml.as_bytes(), &Limits::default()).unwrap();
+        for id in ["n1", "n2", "n3", "notes"] {
+            assert!(node.find_by_id(id).unwrap().element().unwrap().is_note());
+        }
+        assert!(!node
+            .find_by_id("plain")
+            .unwrap()
+            .element()
+            .unwrap()
+            .is_note());
+    }
+
+    #[test]
+    fn entities() {
+        let xml = r#"
+            <html xmlns="http://www.w3.org/1999/xhtml">
+                <body>
+                    <p title="caf&eacute; &amp; bar">A&nbsp;B &mdash; C&hellip; &#233;&#x2014;</p>
+                    <p>&amp;lt; &bogus; & done</p>
+                </body>
+            </html>
+        "#;
+        let node = Node::new(xml.as_bytes(), &Limits::default()).unwrap();
+        let mut iter = node.iter().skip(1);
+        let p = iter.next().unwrap().element().unwrap();
+        assert_eq!(p.attribute("title").unwrap().value(), "café & bar");
+        assert_eq!(iter.next().unwrap().text(), Some("A\u{a0}B — C… é—"));
+        iter.next();
+        assert_eq!(iter.next().unwrap().text(), Some("&lt; &bogus; & done"));
+
+        assert_eq!(unescape("no entities"), "no entities");
+        assert_eq!(unescape("&#xD800; &#65"), "&#xD800; &#65");
+    }
+
+    #[test]
+    fn malformed() {
+        let xml = r#"
+            <html xmlns:xhtml="http://www.w3.org/1999/xhtml">
+                <xhtml:body>
+                    <p>one<br>two</br>
+                    <p>three <i>four</p>
+                    <div>five</span></div>
+                    <p>six
+        "#;
+        let node = Node::new(xml.as_bytes(), &Limits::default()).unwrap();
+        let body = node.element().unwrap();
+        let names: Vec<_> = body
+            .children()
+            .iter()
+            .filter_map(|n| n.element())
+            .map(|e| e.name.as_str())
+            .collect();
+        assert_eq!(names, ["p", "p", "div", "p"]);
+
+        let first = body.children()[0].element().unwrap();
+        assert_eq!(first.children().len(), 3);
+        assert_eq!(
+            first.children()[1].element().unwrap().variant(),
+            ElementVariant::Br
+        );
+
+        let texts: Vec<_> = node.iter().filter_map(|n| n.text()).collect();
+        assert_eq!(texts, ["one", "two", "three", "four", "five", "six"]);
+    }
+
+    #[test]
+    fn too_deep() {
+        let limits = Limits {
+            max_depth: 64,
+            ..Default::default()
+        };
+        let nested = |depth| {
+            format!(
+                "<html><body>{}text{}</body></html>",
+                "<div>".repeat(depth),
+                "</div>".repeat(depth)
+            )
+        };
+        // the body counts as a level
+        assert!(Node::new(nested(63).as_bytes(), &limits).is_ok());
+        let result = Node::new(nested(64).as_bytes(), &limits);
+        assert!(matches!(result, Err(EpubError::TooDeep(64))));
+        // unclosed elements nest too
+        let unclosed = format!("<html><body>{}", "<span>".repeat(100_000));
+        let result = Node::new(unclosed.as_bytes(), &Limits::default());
+        assert!(matches!(result, Err(EpubError::TooDeep(256))));
+    }
+
+    #[test]
+    fn page_breaks() {
+        let html = r#"<html xmlns:epub="http://www.idpf.org/2007/ops"><body>
+            <p>End of one<span epub:type="pagebreak" id="p2" title="2"/>start of two</p>
+            <span role="doc-pagebreak" id="p3" aria-label=" 3 "></span>
+            <div epub:type="pagebreak" id="pxiv">xiv</div>
+            <span epub:type="pagebreak"></span>
+            <span title="5">not a page break</span>
+        </body></html>"#;
+        let node = Node::new(html.as_bytes(), &Limits::default()).unwrap();
+        let labels: Vec<_> = node
+            .iter()
+            .filter_map(|n| n.element().and_then(|e| e.page_break()))
+            .collect();
+        assert_eq!(labels, ["2", "3", "xiv"]);
+    }
 }
@@ -1,62 +1,90 @@
-use std::{path::Path, rc::Rc};
+use std::rc::Rc;
 
-use super::{manifest::Manifest, spine::Spine};
+use crate::document::Location;
+
+use super::{
+    href,
+    manifest::{Manifest, CONTENT_MEDIATYPES},
+    spine::{PageProgression, Spine},
+};
 
 #[derive(Debug, Default)]
 pub struct Index {
-    elements: Vec<Rc<IndexElement>>,
+    elements: Vec<Rc<Location>>,
+    progression: PageProgression,
 }
 impl Index {
-    pub fn new<P: AsRef<Path>>(manifest: Manifest, spine: Spine, contents_dir: P) -> Self {
+    /// Build the reading order. Manifest hrefs are relative to the package document at
+    /// `package_path`. Spine items we can't lay out are replaced by their first fallback
+    /// that we can
+    pub fn new(manifest: &Manifest, spine: Spine, package_path: &str) -> Self {
         let mut elements = vec![];
         for s in spine.items.into_iter() {
-            if let Some(m) = manifest.item(&s) {
-                let path = contents_dir.as_ref().join(&m.href);
-                let path = path.to_str().unwrap();
-                let e = IndexElement {
-                    id: s,
-                    path: path.to_owned(),
-                };
-                elements.push(Rc::new(e));
+            let Some(item) = manifest.item(&s.idref) else {
+                tracing::warn!("spine item {} isn't in the manifest", s.idref);
+                continue;
+            };
+            let m = manifest
+                .fallback_with_mediatypes(item, &CONTENT_MEDIATYPES)
+                .unwrap_or_else(|| {
+                    tracing::warn!("no content document fallback for {}", s.idref);
+                    item
+                });
+            if let Some(path) = href::resolve_path(package_path, &m.href) {
+                elements.push(Rc::new(Location::new(s.idref, path, s.linear)));
             }
         }
-        Self { elements }
+        Self {
+            elements,
+            progression: spine.progression,
+        }
     }
 
-    pub fn element(&self, id: &str) -> Option<Rc<IndexElement>> {
+    pub fn progression(&self) -> PageProgression {
+        self.progression
+    }
+
+    pub fn element_by_path(&self, path: &str) -> Option<Rc<Location>> {
+        self.elements.iter().find(|i| i.path() == path).cloned()
+    }
+    /// The spine item a link found in the document at `from` points to. External links and
+    /// links to resources outside the spine resolve to nothing
+    pub fn resolve(&self, from: &str, href: &str) -> Option<Location> {
+        let href = href::resolve(from, href)?;
+        self.element_by_path(&href.path)
+            .map(|e| e.with_fragment(href.fragment))
+    }
+    /// Every spine item in reading order, linear or not
+    pub fn iter(&self) -> impl Iterator<Item = &Location> {
+        self.elements.iter().map(Rc::as_ref)
+    }
+    /// Position of the spine item in reading order
+    pub fn position(&self, id: &str) -> Option<usize> {
+        self.elements.iter().position(|i| i.id() == id)
+    }
+    /// The first item in reading order, skipping non-linear items unless there's nothing else
+    pub fn first(&self) -> Option<Rc<Location>> {
         self.elements
             .iter()
-            .find(|i| i.id() == id)
-            .map(|c| c.clone())
-    }
-    pub fn first(&self) -> Option<Rc<IndexElement>> {
-        self.elements.first().map(|c| c.clone())
-    }
-    pub fn next(&self, cur: &str) -> Option<Rc<IndexElement>> {
-        let idx = self.elements.iter().position(|i| i.id == cur)?;
-        self.elements.get(idx + 1).map(|c| c.clone())
-    }
-    pub fn prev(&self, cur: &str) -> Option<Rc<IndexElement>> {
-        let idx = self.elements.iter().position(|i| i.id == cur)?;
-        if idx > 0 {
-            self.elements.get(idx - 1).map(|c| c.clone())
-        } else {
-            None
-        }
+            .find(|i| i.is_linear())
+            .or(self.elements.first())
+            .cloned()
     }
-}
-
-#[derive(Debug, Default, Clone, PartialEq)]
-pub struct IndexElement {
-    id: String,
-    path: String,
-}
-impl IndexElement {
-    pub fn id(&self) -> &str {
-        &self.id
+    /// The next linear item, also from a non-linear one reached through a link
+    pub fn next(&self, cur: &str) -> Option<Rc<Location>> {
+        let idx = self.elements.iter().position(|i| i.id() == cur)?;
+        self.elements[idx + 1..]
+            .iter()
+            .find(|i| i.is_linear())
+            .cloned()
     }
-    pub fn path(&self) -> &str {
-        &self.path
+    pub fn prev(&self, cur: &str) -> Option<Rc<Location>> {
+        let idx = self.elements.iter().position(|i| i.id() == cur)?;
+        self.elements[..idx]
+            .iter()
+            .rev()
+            .find(|i| i.is_linear())
+            .cloned()
     }
 }
 
@@ -64,29 +92,64 @@ impl IndexElement {
 mod tests {
     use std::rc::Rc;
 
-    use crate::epub::index::{Index, IndexElement};
+    use quick_xml::Reader;
+
+    use crate::document::Location;
+    use crate::epub::{
+        index::Index,
+        manifest::Manifest,
+        spine::{Itemref, Spine},
+    };
 
-    fn elements() -> Vec<Rc<IndexElement>> {
+    fn elements() -> Vec<Rc<Location>> {
         vec![
-            Rc::new(IndexElement {
-                id: "aaa".to_owned(),
-                path: "".to_owned(),
-            }),
-            Rc::new(IndexElement {
-                id: "bbb".to_owned(),
-                path: "".to_owned(),
-            }),
-            Rc::new(IndexElement {
-                id: "ccc".to_owned(),
-                path: "".to_owned(),
-            }),
+            Rc::new(Location::new("aaa".to_owned(), "".to_owned(), true)),
+            Rc::new(Location::new("bbb".to_owned(), "".to_owned(), true)),
+            Rc::new(Location::new("ccc".to_owned(), "".to_owned(), true)),
         ]
     }
 
+    #[test]
+    fn resolve_links() {
+        let index = Index {
+            progression: Default::default(),
+            elements: vec![
+                Rc::new(Location::new(
+                    "ch1".to_owned(),
+                    "OEBPS/text/ch1.xhtml".to_owned(),
+                    true,
+                )),
+                Rc::new(Location::new(
+                    "notes".to_owned(),
+                    "OEBPS/notes.xhtml".to_owned(),
+                    true,
+                )),
+            ],
+        };
+        let from = index.first().unwrap();
+        let from = from.path();
+
+        let same = index.resolve(from, "#p3").unwrap();
+        assert_eq!("ch1", same.id());
+        assert_eq!(Some("p3"), same.fragment());
+
+        let other = index.resolve(from, "../notes.xhtml#n1").unwrap();
+        assert_eq!("notes", other.id());
+        assert_eq!(Some("n1"), other.fragment());
+
+        let whole = index.resolve(from, "./ch1.xhtml").unwrap();
+        assert_eq!("ch1", whole.id());
+        assert_eq!(None, whole.fragment());
+
+        assert!(index.resolve(from, "https://example.com/#x").is_none());
+        assert!(index.resolve(from, "missing.xhtml").is_none());
+    }
+
     #[test]
     fn first_item_with_elements() {
         let index = Index {
             elements: elements(),
+            ..Default::default()
         };
         let first = index.first().unwrap();
         let first_id = "aaa";
@@ -95,7 +158,7 @@ mod tests {
 
     #[test]
     fn first_item_with_no_elements() {
-        let index = Index { elements: vec![] };
+        let index = Index::default();
         let first = index.first();
         assert!(first.is_none());
     }
@@ -106,6 +169,7 @@ mod tests {
         let next_id = "ccc";
         let index = Index {
             elements: elements(),
+            ..Default::default()
         };
         let next = index.next(cur_id).unwrap();
         assert_eq!(next_id, next.id());
@@ -116,6 +180,7 @@ mod tests {
         let cur_id = "ccc";
         let index = Index {
             elements: elements(),
+            ..Default::default()
         };
         let next = index.next(cur_id);
         assert!(next.is_none());
@@ -127,6 +192,7 @@ mod tests {
         let prev_id = "aaa";
         let index = Index {
             elements: elements(),
+            ..Default::default()
         };
         let prev = index.prev(cur_id).unwrap();
         assert_eq!(prev_id, prev.id());
@@ -137,8 +203,52 @@ mod tests {
         let cur_id = "aaa";
         let index = Index {
             elements: elements(),
+            ..Default::default()
         };
         let prev = index.prev(cur_id);
         assert!(prev.is_none());
     }
+
+    #[test]
+    fn skip_non_linear() {
+        let mut elements = elements();
+        elements[1] = Rc::new(Location::new("bbb".to_owned(), "".to_owned(), false));
+        let index = Index {
+            elements,
+            ..Default::default()
+        };
+        assert_eq!("ccc", index.next("aaa").unwrap().id());
+        assert_eq!("aaa", index.prev("ccc").unwrap().id());
+        // still reachable, and paging carries on from it
+        assert_eq!(Some(1), index.position("bbb"));
+        assert_eq!("ccc", index.next("bbb").unwrap().id());
+        assert_eq!("aaa", index.prev("bbb").unwrap().id());
+    }
+
+    #[test]
+    fn spine_fallback() {
+        let xml = r#"<manifest>
+    <item id="ch1" href="Text/ch1.dtb" media-type="application/x-dtbook+xml" fallback="ch1-html"/>
+    <item id="ch1-html" href="Text/ch1.xhtml" media-type="application/xhtml+xml"/>
+    <item id="ch2" href="Text/ch2.xhtml" media-type="application/xhtml+xml"/>
+  </manifest>"#;
+        let mut reader = Reader::from_str(xml);
+        let _ = reader.read_event();
+        let manifest = Manifest::extract(&mut reader).unwrap();
+        let spine = Spine {
+            items: ["ch1", "ch2"]
+                .map(|id| Itemref {
+                    idref: id.to_owned(),
+                    linear: true,
+                })
+                .to_vec(),
+            ..Default::default()
+        };
+        let index = Index::new(&manifest, spine, "OEBPS/content.opf");
+        let first = index.first().unwrap();
+        // the spine item keeps its id but reads the fallback
+        assert_eq!("ch1", first.id());
+        assert_eq!("OEBPS/Text/ch1.xhtml", first.path());
+        assert_eq!("OEBPS/Text/ch2.xhtml", index.next("ch1").unwrap().path());
+    }
 }
@@ -0,0 +1,193 @@
+use crate::document::Location;
+
+use super::{
+    guide::Guide,
+    index::Index,
+    toc::{self, NavPoint},
+    EpubError,
+};
+
+/// Structural locations in the book, such as where the body matter starts, from the EPUB3
+/// landmarks nav or the EPUB2 guide
+#[derive(Debug, Default)]
+pub struct Landmarks {
+    entries: Vec<Landmark>,
+}
+impl Landmarks {
+    /// Read the `<nav epub:type="landmarks">` of the navigation document at `nav_path`
+    pub fn from_nav(
+        xml: &str,
+        nav_path: &str,
+        index: &Index,
+        max_depth: usize,
+    ) -> Result<Self, EpubError> {
+        let mut entries = vec![];
+        for point in toc::read_nav(xml, "landmarks", max_depth)? {
+            flatten(point, nav_path, index, &mut entries);
+        }
+        Ok(Self { entries })
+    }
+
+    /// Use the references of the guide, whose hrefs are relative to the package document,
+    /// with their EPUB2 types mapped to the EPUB3 ones
+    pub fn from_guide(guide: &Guide, package_path: &str, index: &Index) -> Self {
+        let entries = guide
+            .references()
+            .iter()
+            .filter_map(|r| {
+                let kind = match r.kind.as_str() {
+                    "text" => "bodymatter",
+                    "title-page" => "titlepage",
+                    kind => kind,
+                };
+                Some(Landmark {
+                    kind: kind.to_owned(),
+                    label: r.title.clone(),
+                    location: index.resolve(package_path, &r.href)?,
+                })
+            })
+            .collect();
+        Self { entries }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn entries(&self) -> &[Landmark] {
+        &self.entries
+    }
+
+    /// The first landmark of the given type, e.g. `bodymatter` or `toc`
+    pub fn get(&self, kind: &str) -> Option<&Landmark> {
+        self.entries
+            .iter()
+            .find(|l| l.kind.split_whitespace().any(|k| k == kind))
+    }
+
+    /// Where reading starts, the beginning of the body matter
+    pub fn start(&self) -> Option<&Location> {
+        self.get("bodymatter").map(Landmark::location)
+    }
+
+    /// The table of contents as a page of the book
+    pub fn contents(&self) -> Option<&Location> {
+        self.get("toc").map(Landmark::location)
+    }
+}
+
+/// Landmarks are a flat list, but nothing stops a book from nesting them
+fn flatten(point: NavPoint, nav_path: &str, index: &Index, entries: &mut Vec<Landmark>) {
+    if let (Some(kind), Some(href)) = (point.kind, point.href) {
+        match index.resolve(nav_path, &href) {
+            Some(location) => entries.push(Landmark {
+                kind,
+                label: point.label,
+                location,
+            }),
+            None => tracing::warn!("landmark {} points outside the spine", href),
+        }
+    }
+    for child in point.children {
+        flatten(child, nav_path, index, entries);
+    }
+}
+
+#[derive(Debug)]
+pub struct Landmark {
+    kind: String,
+    label: String,
+    location: Location,
+}
+impl Landmark {
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+    pub fn location(&self) -> &Location {
+        &self.location
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quick_xml::{events::Event, Reader};
+
+    use crate::epub::{
+        guide::Guide,
+        index::Index,
+        manifest::Manifest,
+        spine::{Itemref, Spine},
+    };
+
+    use super::Landmarks;
+
+    fn index() -> Index {
+        let xml = r#"<manifest>
+    <item id="cover" href="Text/cover.xhtml" media-type="application/xhtml+xml"/>
+    <item id="toc" href="Text/toc.xhtml" media-type="application/xhtml+xml"/>
+    <item id="ch1" href="Text/ch1.xhtml" media-type="application/xhtml+xml"/>
+  </manifest>
+        "#;
+        let mut reader = Reader::from_str(xml);
+        let _ = reader.read_event();
+        let manifest = Manifest::extract(&mut reader).unwrap();
+        let spine = Spine {
+            items: ["cover", "toc", "ch1"]
+                .map(|id| Itemref {
+                    idref: id.to_owned(),
+                    linear: true,
+                })
+                .to_vec(),
+            ..Default::default()
+        };
+        Index::new(&manifest, spine, "OEBPS/content.opf")
+    }
+
+    #[test]
+    fn from_nav() {
+        let xml = r#"
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+  <body>
+    <nav epub:type="toc"><ol><li><a href="ch1.xhtml">Chapter One</a></li></ol></nav>
+    <nav epub:type="landmarks">
+      <ol>
+        <li><a epub:type="cover" href="cover.xhtml">Cover</a></li>
+        <li><a epub:type="toc" href="toc.xhtml#contents">Contents</a></li>
+        <li><a epub:type="bodymatter" href="ch1.xhtml">Start Reading</a></li>
+        <li><a epub:type="index" href="missing.xhtml">Index</a></li>
+      </ol>
+    </nav>
+  </body>
+</html>
+        "#;
+        let landmarks = Landmarks::from_nav(xml, "OEBPS/Text/nav.xhtml", &index(), 256).unwrap();
+        assert_eq!(landmarks.entries().len(), 3);
+        assert_eq!(landmarks.get("cover").unwrap().label(), "Cover");
+        assert_eq!(landmarks.start().unwrap().id(), "ch1");
+        let contents = landmarks.contents().unwrap();
+        assert_eq!(contents.path(), "OEBPS/Text/toc.xhtml");
+        assert_eq!(contents.fragment(), Some("contents"));
+        assert!(landmarks.get("index").is_none());
+    }
+
+    #[test]
+    fn from_guide() {
+        let xml = r#"<guide>
+    <reference type="cover" title="Cover" href="Text/cover.xhtml"/>
+    <reference type="toc" title="Table of Contents" href="Text/toc.xhtml"/>
+    <reference type="Text" title="Beginning" href="Text/ch1.xhtml#start"/>
+  </guide>"#;
+        let mut reader = Reader::from_str(xml);
+        assert!(matches!(reader.read_event(), Ok(Event::Start(_))));
+        let guide = Guide::extract(&mut reader).unwrap();
+        let landmarks = Landmarks::from_guide(&guide, "OEBPS/content.opf", &index());
+        let start = landmarks.start().unwrap();
+        assert_eq!(start.id(), "ch1");
+        assert_eq!(start.fragment(), Some("start"));
+        assert_eq!(landmarks.get("bodymatter").unwrap().label(), "Beginning");
+        assert_eq!(landmarks.contents().unwrap().id(), "toc");
+    }
+}
@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+
+/// Bounds on what a book may contain, so a malicious or broken file fails to open instead of
+/// exhausting the stack or memory
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Limits {
+    /// deepest element nesting in a content document
+    pub max_depth: usize,
+    /// largest decompressed size of a single file, in bytes
+    pub max_entry_size: u64,
+    /// largest decompressed size of the whole book, in bytes
+    pub max_total_size: u64,
+    /// most files the container may hold
+    pub max_entries: usize,
+}
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_depth: 256,
+            max_entry_size: 64 * 1024 * 1024,
+            max_total_size: 1024 * 1024 * 1024,
+            max_entries: 10_000,
+        }
+    }
+}
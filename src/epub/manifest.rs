@@ -2,6 +2,9 @@ use quick_xml::{events::Event, Reader};
 
 use super::EpubError;
 
+/// Media types of content documents we can lay out
+pub const CONTENT_MEDIATYPES: [&str; 2] = ["application/xhtml+xml", "text/html"];
+
 #[derive(Debug, Default)]
 pub struct Manifest {
     items: Vec<Item>,
@@ -21,6 +24,8 @@ impl Manifest {
                             b"href" => item.href = val,
                             b"id" => item.id = val,
                             b"media-type" => item.mediatype = val,
+                            b"properties" => item.properties = val,
+                            b"fallback" => item.fallback = Some(val),
                             _ => (),
                         }
                     }
@@ -42,6 +47,47 @@ impl Manifest {
     pub fn item(&self, id: &str) -> Option<&Item> {
         self.items.iter().find(|i| i.id == id)
     }
+    pub fn item_with_mediatype(&self, mediatype: &str) -> Option<&Item> {
+        self.items.iter().find(|i| i.mediatype == mediatype)
+    }
+    /// All items whose media type is one of the given ones
+    pub fn items_with_mediatypes<'a>(
+        &'a self,
+        mediatypes: &'a [&str],
+    ) -> impl Iterator<Item = &'a Item> {
+        self.items
+            .iter()
+            .filter(|i| mediatypes.contains(&i.mediatype.as_str()))
+    }
+    pub fn item_with_property(&self, property: &str) -> Option<&Item> {
+        self.items.iter().find(|i| i.has_property(property))
+    }
+    pub fn items(&self) -> impl Iterator<Item = &Item> {
+        self.items.iter()
+    }
+    /// The item followed by its `fallback` items, in order. A chain that loops back on
+    /// itself ends before repeating an item
+    pub fn fallback_chain<'a>(&'a self, item: &'a Item) -> impl Iterator<Item = &'a Item> {
+        let mut seen = vec![];
+        std::iter::successors(Some(item), move |i| {
+            let next = self.item(i.fallback()?)?;
+            seen.push(i.id.as_str());
+            if seen.contains(&next.id.as_str()) {
+                tracing::warn!("manifest fallback chain loops back to {}", next.id);
+                return None;
+            }
+            Some(next)
+        })
+    }
+    /// The first item in the fallback chain with one of the given media types
+    pub fn fallback_with_mediatypes<'a>(
+        &'a self,
+        item: &'a Item,
+        mediatypes: &[&str],
+    ) -> Option<&'a Item> {
+        self.fallback_chain(item)
+            .find(|i| mediatypes.contains(&i.mediatype()))
+    }
 }
 
 #[derive(Debug, Default)]
@@ -49,6 +95,26 @@ pub struct Item {
     id: String,
     pub href: String,
     mediatype: String,
+    properties: String,
+    fallback: Option<String>,
+}
+impl Item {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    pub fn mediatype(&self) -> &str {
+        &self.mediatype
+    }
+    pub fn properties(&self) -> impl Iterator<Item = &str> {
+        self.properties.split_whitespace()
+    }
+    pub fn has_property(&self, property: &str) -> bool {
+        self.properties().any(|p| p == property)
+    }
+    /// Id of the item to use instead when this one's media type isn't supported
+    pub fn fallback(&self) -> Option<&str> {
+        self.fallback.as_deref()
+    }
 }
 
 #[cfg(test)]
@@ -57,7 +123,13 @@ mod tests {
     use quick_xml::events::Event;
     use quick_xml::Reader;
 
-    use super::Manifest;
+    use super::{Manifest, CONTENT_MEDIATYPES};
+
+    fn manifest(xml: &str) -> Manifest {
+        let mut reader = Reader::from_str(xml);
+        let _ = reader.read_event();
+        Manifest::extract(&mut reader).unwrap()
+    }
 
     #[test]
     fn happy_path() {
@@ -67,6 +139,7 @@ mod tests {
     <item id="cover" href="cover.jpeg" media-type="image/jpeg"/>
     <item id="titlepage" href="titlepage.xhtml" media-type="application/xhtml+xml"/>
     <item id="id5" href="text/part0000.html" media-type="application/xhtml+xml"/>
+    <item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav scripted"/>
   </manifest>
         "#;
         let mut reader = Reader::from_str(xml);
@@ -74,13 +147,42 @@ mod tests {
             match reader.read_event() {
                 Ok(Event::Start(ref e)) if e.name().as_ref() == b"manifest" => {
                     let result = Manifest::extract(&mut reader).unwrap();
-                    assert_eq!(result.items.len(), 3);
+                    assert_eq!(result.items.len(), 4);
                     assert_eq!(result.items[0].id, "cover");
                     assert_eq!(result.items[1].href, "titlepage.xhtml");
+                    assert_eq!(result.item_with_property("nav").unwrap().id, "nav");
                 }
                 Ok(Event::Eof) => break, // exits the loop when reaching end of file
                 _ => (),
             }
         }
     }
+
+    #[test]
+    fn fallback_chain() {
+        let manifest = manifest(
+            r#"<manifest>
+    <item id="dtb" href="ch1.dtb" media-type="application/x-dtbook+xml" fallback="svg"/>
+    <item id="svg" href="ch1.svg" media-type="image/svg+xml" fallback="xhtml"/>
+    <item id="xhtml" href="ch1.xhtml" media-type="application/xhtml+xml"/>
+    <item id="a" href="a.svg" media-type="image/svg+xml" fallback="b"/>
+    <item id="b" href="b.svg" media-type="image/svg+xml" fallback="a"/>
+  </manifest>"#,
+        );
+        let dtb = manifest.item("dtb").unwrap();
+        assert_eq!(dtb.mediatype(), "application/x-dtbook+xml");
+        assert_eq!(dtb.fallback(), Some("svg"));
+        let chain: Vec<_> = manifest.fallback_chain(dtb).map(|i| i.id()).collect();
+        assert_eq!(chain, ["dtb", "svg", "xhtml"]);
+        let content = manifest.fallback_with_mediatypes(dtb, &CONTENT_MEDIATYPES);
+        assert_eq!(content.unwrap().href, "ch1.xhtml");
+
+        // loops end instead of going round forever
+        let a = manifest.item("a").unwrap();
+        let chain: Vec<_> = manifest.fallback_chain(a).map(|i| i.id()).collect();
+        assert_eq!(chain, ["a", "b"]);
+        assert!(manifest
+            .fallback_with_mediatypes(a, &CONTENT_MEDIATYPES)
+            .is_none());
+    }
 }
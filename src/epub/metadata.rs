@@ -1,4 +1,6 @@
-use quick_xml::events::Event;
+use std::collections::HashMap;
+
+use quick_xml::events::{BytesStart, Event};
 use quick_xml::Reader;
 use serde::Deserialize;
 
@@ -6,33 +8,124 @@ use super::EpubError;
 
 #[derive(Debug, Default, Deserialize)]
 pub struct Metadata {
-    title: Option<String>,
-    language: Option<String>,
-    author: Option<String>,
-    identifier: Option<String>,
-    published: Option<String>,
+    titles: Vec<String>,
+    languages: Vec<String>,
+    creators: Vec<Contributor>,
+    contributors: Vec<Contributor>,
+    identifiers: Vec<Identifier>,
+    subjects: Vec<String>,
+    description: Option<String>,
+    publisher: Option<String>,
+    date: Option<String>,
+    modified: Option<String>,
+    series: Option<Series>,
+    cover: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+pub struct Contributor {
+    name: String,
+    role: Option<String>,
+    file_as: Option<String>,
+}
+impl Contributor {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    /// MARC relator code, e.g. `aut`, `edt`, `trl`
+    pub fn role(&self) -> Option<&str> {
+        self.role.as_deref()
+    }
+    /// The name to sort by, falling back to the display name
+    pub fn sort_name(&self) -> &str {
+        self.file_as.as_deref().unwrap_or(&self.name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub enum IdentifierScheme {
+    Isbn,
+    Uuid,
+    Doi,
+    Uri,
+    Other(String),
+}
+impl IdentifierScheme {
+    fn from_scheme(value: &str) -> Self {
+        match value.to_lowercase().as_ref() {
+            "isbn" | "15" => Self::Isbn,
+            "uuid" => Self::Uuid,
+            "doi" | "06" => Self::Doi,
+            "uri" | "url" => Self::Uri,
+            _ => Self::Other(value.to_owned()),
+        }
+    }
+    fn sniff(value: &str) -> Option<Self> {
+        let lower = value.to_lowercase();
+        if lower.starts_with("urn:isbn:") {
+            Some(Self::Isbn)
+        } else if lower.starts_with("urn:uuid:") {
+            Some(Self::Uuid)
+        } else if lower.starts_with("urn:doi:") || lower.starts_with("doi:") {
+            Some(Self::Doi)
+        } else if lower.starts_with("http://") || lower.starts_with("https://") {
+            Some(Self::Uri)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Identifier {
+    id: Option<String>,
+    value: String,
+    scheme: Option<IdentifierScheme>,
+}
+impl Identifier {
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+    /// The identifier as written in the package document
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+    pub fn scheme(&self) -> Option<&IdentifierScheme> {
+        self.scheme.as_ref()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Series {
+    name: String,
+    index: Option<f32>,
+}
+impl Series {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn index(&self) -> Option<f32> {
+        self.index
+    }
 }
 
 impl Metadata {
     pub fn extract(reader: &mut Reader<&[u8]>) -> Result<Self, EpubError> {
         let mut depth = 1;
-        let mut metadata = Self::default();
+        let mut elements = vec![];
         loop {
             match reader.read_event() {
-                Ok(Event::Start(ref e)) => {
-                    depth += 1;
-                    let key = e.name().local_name();
-                    if let Ok(Event::Text(text)) = reader.read_event() {
-                        let value = std::str::from_utf8(&text).map(ToOwned::to_owned).ok();
-                        match key.as_ref() {
-                            b"title" => metadata.title = value,
-                            b"language" => metadata.language = value,
-                            b"identifier" => metadata.identifier = value,
-                            b"creator" => metadata.author = value,
-                            b"published" => metadata.published = value,
-                            _ => (),
-                        }
+                Ok(Event::Start(ref e)) => match e.local_name().as_ref() {
+                    // OPF 1.x wraps its metadata in these
+                    b"dc-metadata" | b"x-metadata" => depth += 1,
+                    _ => {
+                        let mut element = RawElement::new(e)?;
+                        element.text = read_text(reader)?;
+                        elements.push(element);
                     }
+                },
+                Ok(Event::Empty(ref e)) => {
+                    elements.push(RawElement::new(e)?);
                 }
                 Ok(Event::End(_)) => {
                     depth -= 1;
@@ -45,8 +138,224 @@ impl Metadata {
                 _ => {}
             }
         }
-        Ok(metadata)
+        Ok(Self::from_elements(&elements))
+    }
+
+    fn from_elements(elements: &[RawElement]) -> Self {
+        let mut metadata = Self::default();
+
+        // EPUB3 `<meta refines="#id">` elements, keyed by the id they refine
+        let mut refinements: HashMap<&str, Vec<&RawElement>> = HashMap::new();
+        for e in elements.iter().filter(|e| e.name == "meta") {
+            if let Some(id) = e.attr("refines") {
+                let id = id.trim_start_matches('#');
+                refinements.entry(id).or_default().push(e);
+            }
+        }
+        let refinement = |e: &RawElement, property: &str| {
+            let id = e.attr("id")?;
+            refinements
+                .get(id)?
+                .iter()
+                .find(|r| r.attr("property") == Some(property))
+                .map(|r| r.text.as_str())
+        };
+
+        let mut calibre_series = None;
+        let mut calibre_index = None;
+        for e in elements {
+            if e.text.is_empty() && e.name != "meta" {
+                continue;
+            }
+            match e.name.as_str() {
+                "title" => {
+                    if refinement(e, "title-type") == Some("main") {
+                        metadata.titles.insert(0, e.text.clone());
+                    } else {
+                        metadata.titles.push(e.text.clone());
+                    }
+                }
+                "language" => metadata.languages.push(e.text.clone()),
+                "creator" | "contributor" => {
+                    let contributor = Contributor {
+                        name: e.text.clone(),
+                        role: e.attr("role").or(refinement(e, "role")).map(Into::into),
+                        file_as: e
+                            .attr("file-as")
+                            .or(refinement(e, "file-as"))
+                            .map(Into::into),
+                    };
+                    if e.name == "creator" {
+                        metadata.creators.push(contributor);
+                    } else {
+                        metadata.contributors.push(contributor);
+                    }
+                }
+                "identifier" => {
+                    let scheme = e
+                        .attr("scheme")
+                        .or(refinement(e, "identifier-type"))
+                        .map(IdentifierScheme::from_scheme)
+                        .or_else(|| IdentifierScheme::sniff(&e.text));
+                    metadata.identifiers.push(Identifier {
+                        id: e.attr("id").map(Into::into),
+                        value: e.text.clone(),
+                        scheme,
+                    });
+                }
+                "subject" => metadata.subjects.push(e.text.clone()),
+                "description" => metadata.description = Some(e.text.clone()),
+                "publisher" => metadata.publisher = Some(e.text.clone()),
+                "date" => {
+                    // EPUB2 allows several dates distinguished by `opf:event`
+                    let event = e.attr("event");
+                    if metadata.date.is_none() || event == Some("publication") {
+                        metadata.date = Some(e.text.clone());
+                    }
+                }
+                "meta" if e.attr("refines").is_none() => match e.attr("property") {
+                    Some("dcterms:modified") => metadata.modified = Some(e.text.clone()),
+                    Some("belongs-to-collection") => {
+                        let collection_type = refinement(e, "collection-type");
+                        if collection_type.is_none() || collection_type == Some("series") {
+                            metadata.series.get_or_insert(Series {
+                                name: e.text.clone(),
+                                index: refinement(e, "group-position")
+                                    .and_then(|p| p.trim().parse().ok()),
+                            });
+                        }
+                    }
+                    _ => match e.attr("name") {
+                        Some("calibre:series") => calibre_series = e.attr("content"),
+                        Some("calibre:series_index") => calibre_index = e.attr("content"),
+                        Some("cover") => metadata.cover = e.attr("content").map(Into::into),
+                        _ => (),
+                    },
+                },
+                _ => (),
+            }
+        }
+        if let (None, Some(name)) = (&metadata.series, calibre_series) {
+            metadata.series = Some(Series {
+                name: name.to_owned(),
+                index: calibre_index.and_then(|i| i.trim().parse().ok()),
+            });
+        }
+        metadata
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.titles.first().map(|t| t.as_str())
+    }
+    pub fn titles(&self) -> &[String] {
+        &self.titles
+    }
+    pub fn language(&self) -> Option<&str> {
+        self.languages.first().map(|l| l.as_str())
+    }
+    pub fn languages(&self) -> &[String] {
+        &self.languages
+    }
+    pub fn creators(&self) -> &[Contributor] {
+        &self.creators
+    }
+    pub fn contributors(&self) -> &[Contributor] {
+        &self.contributors
+    }
+    /// Creators credited as authors, which is all of them when no roles are given
+    pub fn authors(&self) -> impl Iterator<Item = &Contributor> {
+        self.creators
+            .iter()
+            .filter(|c| c.role.as_deref().is_none_or(|r| r == "aut"))
     }
+    pub fn identifiers(&self) -> &[Identifier] {
+        &self.identifiers
+    }
+    pub fn identifier(&self, id: &str) -> Option<&Identifier> {
+        self.identifiers.iter().find(|i| i.id() == Some(id))
+    }
+    pub fn isbn(&self) -> Option<&str> {
+        self.identifiers
+            .iter()
+            .find(|i| i.scheme == Some(IdentifierScheme::Isbn))
+            .map(|i| i.value.trim_start_matches("urn:isbn:"))
+    }
+    pub fn subjects(&self) -> &[String] {
+        &self.subjects
+    }
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+    pub fn publisher(&self) -> Option<&str> {
+        self.publisher.as_deref()
+    }
+    pub fn date(&self) -> Option<&str> {
+        self.date.as_deref()
+    }
+    pub fn modified(&self) -> Option<&str> {
+        self.modified.as_deref()
+    }
+    pub fn series(&self) -> Option<&Series> {
+        self.series.as_ref()
+    }
+    /// Manifest id of the cover image, as given by EPUB2 `<meta name="cover">`
+    pub fn cover(&self) -> Option<&str> {
+        self.cover.as_deref()
+    }
+}
+
+/// A metadata element as written in the package document
+#[derive(Debug)]
+struct RawElement {
+    name: String,
+    attributes: Vec<(String, String)>,
+    text: String,
+}
+impl RawElement {
+    fn new(tag: &BytesStart) -> Result<Self, EpubError> {
+        let name = std::str::from_utf8(tag.local_name().into_inner())?.to_owned();
+        let mut attributes = vec![];
+        for attr in tag.attributes() {
+            let attr = attr?;
+            let key = std::str::from_utf8(attr.key.local_name().into_inner())?.to_owned();
+            let value = attr.unescape_value()?.trim().to_owned();
+            attributes.push((key, value));
+        }
+        Ok(Self {
+            name,
+            attributes,
+            text: String::new(),
+        })
+    }
+    /// Look up an attribute by its local name, ignoring any namespace prefix
+    fn attr(&self, key: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+fn read_text(reader: &mut Reader<&[u8]>) -> Result<String, EpubError> {
+    let mut depth = 1;
+    let mut text = String::new();
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(_)) => depth += 1,
+            Ok(Event::Text(t)) => text.push_str(&t.unescape()?),
+            Ok(Event::CData(t)) => text.push_str(std::str::from_utf8(&t)?),
+            Ok(Event::End(_)) => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            Ok(Event::Eof) => return Err(EpubError::UnexpectedEof),
+            Err(e) => return Err(e.into()),
+            _ => {}
+        }
+    }
+    Ok(text.split_whitespace().collect::<Vec<_>>().join(" "))
 }
 
 #[cfg(test)]
@@ -55,7 +364,7 @@ mod tests {
     use quick_xml::events::Event;
     use quick_xml::Reader;
 
-    use super::Metadata;
+    use super::{IdentifierScheme, Metadata};
 
     #[test]
     fn happy_path() {
@@ -73,9 +382,100 @@ mod tests {
             match reader.read_event() {
                 Ok(Event::Start(ref e)) if e.name().as_ref() == b"metadata" => {
                     let result = Metadata::extract(&mut reader).unwrap();
-                    assert_eq!(result.title, Some("Pride and Prejudice".to_owned()));
-                    assert_eq!(result.author, Some("Jane Austen".to_owned()));
-                    assert!(result.published.is_none());
+                    assert_eq!(result.title(), Some("Pride and Prejudice"));
+                    let author = result.authors().next().unwrap();
+                    assert_eq!(author.name(), "Jane Austen");
+                    assert_eq!(author.sort_name(), "Austen, Jane");
+                    assert_eq!(result.isbn(), Some("123456789X"));
+                    assert!(result.date().is_none());
+                }
+                Ok(Event::Eof) => break, // exits the loop when reaching end of file
+                _ => (),
+            }
+        }
+    }
+
+    #[test]
+    fn epub2_attributes() {
+        let xml = r#"
+<?xml version='1.0' encoding='utf-8'?>
+  <metadata xmlns:opf='http://www.idpf.org/2007/opf' xmlns:dc='http://purl.org/dc/elements/1.1/'>
+    <dc:title>The Annotated Alice</dc:title>
+    <dc:creator opf:file-as='Carroll, Lewis' opf:role='aut'>Lewis Carroll</dc:creator>
+    <dc:creator opf:role='ill'>John Tenniel</dc:creator>
+    <dc:contributor opf:role='edt'>Martin Gardner</dc:contributor>
+    <dc:identifier opf:scheme='UUID'>urn:uuid:a0b1c2d3</dc:identifier>
+    <dc:subject>Fantasy</dc:subject>
+    <dc:subject>Children's stories</dc:subject>
+    <dc:publisher>Norton</dc:publisher>
+    <dc:description>With notes &amp; commentary</dc:description>
+    <dc:date opf:event='conversion'>2020-01-01</dc:date>
+    <dc:date opf:event='publication'>1960</dc:date>
+    <meta name='calibre:series' content='Annotated Classics'/>
+    <meta name='calibre:series_index' content='3.0'/>
+    <meta name='cover' content='cover-image'/>
+  </metadata>
+        "#;
+        let mut reader = Reader::from_str(xml);
+        loop {
+            match reader.read_event() {
+                Ok(Event::Start(ref e)) if e.name().as_ref() == b"metadata" => {
+                    let result = Metadata::extract(&mut reader).unwrap();
+                    assert_eq!(result.creators().len(), 2);
+                    assert_eq!(result.authors().count(), 1);
+                    assert_eq!(result.contributors()[0].role(), Some("edt"));
+                    assert_eq!(
+                        result.identifiers()[0].scheme(),
+                        Some(&IdentifierScheme::Uuid)
+                    );
+                    assert_eq!(result.subjects().len(), 2);
+                    assert_eq!(result.publisher(), Some("Norton"));
+                    assert_eq!(result.description(), Some("With notes & commentary"));
+                    assert_eq!(result.date(), Some("1960"));
+                    let series = result.series().unwrap();
+                    assert_eq!(series.name(), "Annotated Classics");
+                    assert_eq!(series.index(), Some(3.0));
+                    assert_eq!(result.cover(), Some("cover-image"));
+                }
+                Ok(Event::Eof) => break, // exits the loop when reaching end of file
+                _ => (),
+            }
+        }
+    }
+
+    #[test]
+    fn epub3_refines() {
+        let xml = r##"
+<?xml version='1.0' encoding='utf-8'?>
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="pub-id">9780000000002</dc:identifier>
+    <meta refines="#pub-id" property="identifier-type" scheme="onix:codelist5">15</meta>
+    <dc:title id="t2">A Subtitle</dc:title>
+    <dc:title id="t1">The Main Title</dc:title>
+    <meta refines="#t1" property="title-type">main</meta>
+    <dc:creator id="author_0">Mary Wollstonecraft Shelley</dc:creator>
+    <meta property="file-as" refines="#author_0">Shelley, Mary Wollstonecraft</meta>
+    <meta property="role" refines="#author_0" scheme="marc:relators">aut</meta>
+    <meta property="dcterms:modified">2024-10-01T07:56:37Z</meta>
+    <meta property="belongs-to-collection" id="c01">The Expanse</meta>
+    <meta refines="#c01" property="collection-type">series</meta>
+    <meta refines="#c01" property="group-position">2</meta>
+  </metadata>
+        "##;
+        let mut reader = Reader::from_str(xml);
+        loop {
+            match reader.read_event() {
+                Ok(Event::Start(ref e)) if e.name().as_ref() == b"metadata" => {
+                    let result = Metadata::extract(&mut reader).unwrap();
+                    assert_eq!(result.title(), Some("The Main Title"));
+                    assert_eq!(result.isbn(), Some("9780000000002"));
+                    let author = &result.creators()[0];
+                    assert_eq!(author.role(), Some("aut"));
+                    assert_eq!(author.sort_name(), "Shelley, Mary Wollstonecraft");
+                    assert_eq!(result.modified(), Some("2024-10-01T07:56:37Z"));
+                    let series = result.series().unwrap();
+                    assert_eq!(series.name(), "The Expanse");
+                    assert_eq!(series.index(), Some(2.0));
                 }
                 Ok(Event::Eof) => break, // exits the loop when reaching end of file
                 _ => (),
@@ -98,7 +498,7 @@ mod tests {
             match reader.read_event() {
                 Ok(Event::Start(ref e)) if e.name().as_ref() == b"metadata" => {
                     let result = Metadata::extract(&mut reader).unwrap();
-                    assert!(result.title.is_none());
+                    assert!(result.title().is_none());
                 }
                 Ok(Event::Eof) => break, // exits the loop when reaching end of file
                 _ => (),
@@ -0,0 +1,62 @@
+use quick_xml::{events::Event, Reader};
+
+use super::{
+    encoding::decode, guide::Guide, manifest::Manifest, metadata::Metadata, source::Source,
+    spine::Spine, EpubError,
+};
+
+/// The package document, the OPF file a rootfile in `META-INF/container.xml` points to
+#[derive(Debug, Default)]
+pub struct Package {
+    /// where the package document is in the container, which manifest hrefs are relative to
+    pub path: String,
+    /// id of the metadata identifier that uniquely identifies the book
+    pub unique_identifier: Option<String>,
+    pub metadata: Metadata,
+    pub manifest: Manifest,
+    pub spine: Spine,
+    pub guide: Guide,
+}
+impl Package {
+    /// Read and parse the package document at `rootfile_path`
+    pub fn read(epub: &mut dyn Source, rootfile_path: &str) -> Result<Self, EpubError> {
+        let mut file_bytes = Vec::new();
+        epub.read(rootfile_path, &mut file_bytes)?;
+        let rootfile_contents = decode(&file_bytes)?;
+        let mut package = Self::from_xml(&rootfile_contents)?;
+        package.path = rootfile_path.to_owned();
+        Ok(package)
+    }
+
+    pub fn from_xml(xml: &str) -> Result<Self, EpubError> {
+        let mut reader = Reader::from_str(xml);
+        let mut package = Self::default();
+        loop {
+            match reader.read_event() {
+                Ok(Event::Start(ref e)) => match e.name().as_ref() {
+                    b"package" => {
+                        if let Some(attr) = e.try_get_attribute("unique-identifier")? {
+                            package.unique_identifier = Some(attr.unescape_value()?.into_owned());
+                        }
+                    }
+                    b"metadata" => {
+                        package.metadata = Metadata::extract(&mut reader)?;
+                    }
+                    b"manifest" => {
+                        package.manifest = Manifest::extract(&mut reader)?;
+                    }
+                    b"spine" => {
+                        package.spine = Spine::extract(e, &mut reader)?;
+                    }
+                    b"guide" => {
+                        package.guide = Guide::extract(&mut reader)?;
+                    }
+                    _ => (),
+                },
+                Ok(Event::Eof) => break, // exits the loop when reaching end of file
+                _ => (),
+            }
+        }
+        Ok(package)
+    }
+}
@@ -0,0 +1,172 @@
+use crate::document::Location;
+
+use super::{
+    index::Index,
+    toc::{self, NavPoint},
+    EpubError,
+};
+
+/// Where the pages of the print edition begin, from the EPUB3 page-list nav or the EPUB2
+/// NCX `pageList`
+#[derive(Debug, Default)]
+pub struct PageList {
+    entries: Vec<PrintPage>,
+}
+impl PageList {
+    /// Read the `<nav epub:type="page-list">` of the navigation document at `nav_path`
+    pub fn from_nav(
+        xml: &str,
+        nav_path: &str,
+        index: &Index,
+        max_depth: usize,
+    ) -> Result<Self, EpubError> {
+        let points = toc::read_nav(xml, "page-list", max_depth)?;
+        Ok(Self::resolve(points, nav_path, index))
+    }
+
+    /// Read the `<pageList>` of the NCX document at `ncx_path`
+    pub fn from_ncx(
+        xml: &str,
+        ncx_path: &str,
+        index: &Index,
+        max_depth: usize,
+    ) -> Result<Self, EpubError> {
+        let points = toc::read_page_list(xml, max_depth)?;
+        Ok(Self::resolve(points, ncx_path, index))
+    }
+
+    fn resolve(points: Vec<NavPoint>, from: &str, index: &Index) -> Self {
+        let entries = points
+            .into_iter()
+            .filter_map(|p| {
+                let href = p.href?;
+                match index.resolve(from, &href) {
+                    Some(location) => Some(PrintPage {
+                        label: p.label,
+                        location,
+                    }),
+                    None => {
+                        tracing::warn!("print page {} points outside the spine", p.label);
+                        None
+                    }
+                }
+            })
+            .collect();
+        Self { entries }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn entries(&self) -> &[PrintPage] {
+        &self.entries
+    }
+
+    /// The print page with the given label. Front matter is often numbered in roman
+    /// numerals, so letters match regardless of case
+    pub fn get(&self, label: &str) -> Option<&PrintPage> {
+        self.entries
+            .iter()
+            .find(|p| p.label.eq_ignore_ascii_case(label.trim()))
+    }
+}
+
+#[derive(Debug)]
+pub struct PrintPage {
+    label: String,
+    location: Location,
+}
+impl PrintPage {
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+    pub fn location(&self) -> &Location {
+        &self.location
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quick_xml::Reader;
+
+    use crate::epub::{
+        index::Index,
+        manifest::Manifest,
+        spine::{Itemref, Spine},
+    };
+
+    use super::PageList;
+
+    fn index() -> Index {
+        let xml = r#"<manifest>
+    <item id="front" href="Text/front.xhtml" media-type="application/xhtml+xml"/>
+    <item id="ch1" href="Text/ch1.xhtml" media-type="application/xhtml+xml"/>
+  </manifest>
+        "#;
+        let mut reader = Reader::from_str(xml);
+        let _ = reader.read_event();
+        let manifest = Manifest::extract(&mut reader).unwrap();
+        let spine = Spine {
+            items: ["front", "ch1"]
+                .map(|id| Itemref {
+                    idref: id.to_owned(),
+                    linear: true,
+                })
+                .to_vec(),
+            ..Default::default()
+        };
+        Index::new(&manifest, spine, "OEBPS/content.opf")
+    }
+
+    #[test]
+    fn from_nav() {
+        let xml = r#"
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+  <body>
+    <nav epub:type="toc"><ol><li><a href="ch1.xhtml">Chapter One</a></li></ol></nav>
+    <nav epub:type="page-list" hidden="">
+      <ol>
+        <li><a href="front.xhtml#pii">ii</a></li>
+        <li><a href="ch1.xhtml#p1">1</a></li>
+        <li><a href="ch1.xhtml#p2">2</a></li>
+        <li><a href="missing.xhtml#p3">3</a></li>
+      </ol>
+    </nav>
+  </body>
+</html>
+        "#;
+        let pages = PageList::from_nav(xml, "OEBPS/Text/nav.xhtml", &index(), 256).unwrap();
+        assert_eq!(pages.entries().len(), 3);
+        let second = pages.get("2").unwrap().location();
+        assert_eq!(second.id(), "ch1");
+        assert_eq!(second.fragment(), Some("p2"));
+        assert_eq!(pages.get("II").unwrap().location().id(), "front");
+        assert!(pages.get("3").is_none());
+    }
+
+    #[test]
+    fn from_ncx() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/" version="2005-1">
+  <navMap>
+    <navPoint id="np1" playOrder="1">
+      <navLabel><text>Chapter One</text></navLabel><content src="Text/ch1.xhtml"/>
+    </navPoint>
+  </navMap>
+  <pageList>
+    <navLabel><text>Pages</text></navLabel>
+    <pageTarget id="pt2" type="normal" value="2" playOrder="3">
+      <navLabel><text>2</text></navLabel><content src="Text/ch1.xhtml#p2"/>
+    </pageTarget>
+    <pageTarget id="pt1" type="normal" value="1" playOrder="2">
+      <navLabel><text>1</text></navLabel><content src="Text/ch1.xhtml#p1"/>
+    </pageTarget>
+  </pageList>
+</ncx>"#;
+        let pages = PageList::from_ncx(xml, "OEBPS/toc.ncx", &index(), 256).unwrap();
+        let labels: Vec<_> = pages.entries().iter().map(|p| p.label()).collect();
+        assert_eq!(labels, ["1", "2"]);
+        assert_eq!(pages.get("1").unwrap().location().fragment(), Some("p1"));
+    }
+}
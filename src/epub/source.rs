@@ -0,0 +1,231 @@
+use std::{
+    fmt::Debug,
+    fs::File,
+    io::{ErrorKind, Read},
+    path::{Path, PathBuf},
+};
+
+use super::{limits::Limits, EpubError};
+
+/// Where the files of a book are read from, such as a zip archive or an unpacked directory
+pub trait Source: Debug {
+    /// Read the file at `path` inside the container into `buf`, replacing what it held
+    fn read(&mut self, path: &str, buf: &mut Vec<u8>) -> Result<(), EpubError>;
+    /// Paths of all the files in the container
+    fn files(&self) -> Vec<String>;
+}
+
+/// An unpacked book, as it's laid out while authoring
+#[derive(Debug)]
+pub struct DirSource {
+    root: PathBuf,
+    max_entry_size: u64,
+}
+impl DirSource {
+    pub fn new<P: AsRef<Path>>(root: P, limits: &Limits) -> Self {
+        Self {
+            root: root.as_ref().to_owned(),
+            max_entry_size: limits.max_entry_size,
+        }
+    }
+}
+impl Source for DirSource {
+    fn read(&mut self, path: &str, buf: &mut Vec<u8>) -> Result<(), EpubError> {
+        if !is_contained(path) {
+            return Err(EpubError::PathTraversal(path.to_owned()));
+        }
+        let file = match File::open(self.root.join(path)) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                return Err(EpubError::MissingResource(path.to_owned()))
+            }
+            Err(e) => return Err(e.into()),
+        };
+        read_limited(file, path, self.max_entry_size, buf)
+    }
+
+    fn files(&self) -> Vec<String> {
+        let mut files = vec![];
+        let mut dirs = vec![self.root.clone()];
+        while let Some(dir) = dirs.pop() {
+            let Ok(entries) = std::fs::read_dir(&dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.is_dir() {
+                    dirs.push(path);
+                } else if let Ok(relative) = path.strip_prefix(&self.root) {
+                    let segments: Vec<_> = relative.iter().map(|s| s.to_string_lossy()).collect();
+                    files.push(segments.join("/"));
+                }
+            }
+        }
+        files.sort();
+        files
+    }
+}
+
+/// Whether a path stays inside the container: relative, without `..` segments or a drive
+pub(super) fn is_contained(path: &str) -> bool {
+    let mut segments = path.split(['/', '\\']);
+    let first = segments.next().unwrap_or_default();
+    !first.is_empty() && !first.contains(':') && first != ".." && segments.all(|s| s != "..")
+}
+
+/// Read a whole entry into `buf`, failing once it grows past `max_size` bytes
+pub(super) fn read_limited<R: Read>(
+    reader: R,
+    path: &str,
+    max_size: u64,
+    buf: &mut Vec<u8>,
+) -> Result<(), EpubError> {
+    buf.clear();
+    reader.take(max_size + 1).read_to_end(buf)?;
+    if buf.len() as u64 > max_size {
+        buf.clear();
+        return Err(EpubError::EntryTooLarge(path.to_owned(), max_size));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::File,
+        io::{Cursor, Write},
+    };
+
+    use crate::epub::{limits::Limits, zip::ZipSource, EpubError};
+
+    use super::{is_contained, DirSource, Source};
+
+    #[test]
+    fn dir_matches_zip() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = File::open("testfiles/epubs/pride_and_prejudice.epub").unwrap();
+        let mut zip = ZipSource::new(file, &Limits::default()).unwrap();
+        zip::ZipArchive::new(File::open("testfiles/epubs/pride_and_prejudice.epub").unwrap())
+            .unwrap()
+            .extract(&dir)
+            .unwrap();
+        let mut dir_source = DirSource::new(dir.path(), &Limits::default());
+
+        let (mut a, mut b) = (vec![], vec![]);
+        for path in ["META-INF/container.xml", "OEBPS/content.opf"] {
+            zip.read(path, &mut a).unwrap();
+            dir_source.read(path, &mut b).unwrap();
+            assert!(!a.is_empty());
+            assert_eq!(a, b);
+        }
+        let mut zip_files = zip.files();
+        zip_files.sort();
+        assert_eq!(zip_files, dir_source.files());
+        assert!(zip_files.contains(&"OEBPS/content.opf".to_owned()));
+        for source in [&mut zip as &mut dyn Source, &mut dir_source] {
+            let missing = source.read("OEBPS/missing.xhtml", &mut a);
+            assert!(
+                matches!(missing, Err(EpubError::MissingResource(p)) if p == "OEBPS/missing.xhtml")
+            );
+        }
+    }
+
+    #[test]
+    fn contained_paths() {
+        assert!(is_contained("OEBPS/Text/ch1.xhtml"));
+        assert!(is_contained("mimetype"));
+        assert!(is_contained("OEBPS/..hidden/a..b.png"));
+        assert!(!is_contained("../etc/passwd"));
+        assert!(!is_contained("OEBPS/../../etc/passwd"));
+        assert!(!is_contained("OEBPS\\..\\..\\boot.ini"));
+        assert!(!is_contained("/etc/passwd"));
+        assert!(!is_contained("C:/Windows/win.ini"));
+        assert!(!is_contained(""));
+    }
+
+    #[test]
+    fn entry_size() {
+        let limits = Limits {
+            max_entry_size: 100,
+            ..Default::default()
+        };
+        let file = File::open("testfiles/epubs/pride_and_prejudice.epub").unwrap();
+        let mut zip = ZipSource::new(file, &limits).unwrap();
+        let mut buf = vec![];
+        zip.read("mimetype", &mut buf).unwrap();
+        assert!(matches!(
+            zip.read("OEBPS/content.opf", &mut buf),
+            Err(EpubError::EntryTooLarge(p, 100)) if p == "OEBPS/content.opf"
+        ));
+    }
+
+    #[test]
+    fn archive_limits() {
+        let path = "testfiles/epubs/pride_and_prejudice.epub";
+        let few_entries = Limits {
+            max_entries: 3,
+            ..Default::default()
+        };
+        let small = Limits {
+            max_total_size: 1024,
+            ..Default::default()
+        };
+        assert!(matches!(
+            ZipSource::new(File::open(path).unwrap(), &few_entries),
+            Err(EpubError::TooManyEntries(3))
+        ));
+        assert!(matches!(
+            ZipSource::new(File::open(path).unwrap(), &small),
+            Err(EpubError::TooLarge(1024))
+        ));
+    }
+
+    #[test]
+    fn lying_sizes() {
+        let mut zip = zip::ZipWriter::new(Cursor::new(vec![]));
+        let options = zip::write::SimpleFileOptions::default();
+        for name in ["a.xhtml", "b.xhtml"] {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(&[b'a'; 800]).unwrap();
+        }
+        let mut data = zip.finish().unwrap().into_inner();
+        // claim each entry unpacks to 10 bytes, in both the local and central headers
+        for i in 0..data.len() - 4 {
+            let offset = match &data[i..i + 4] {
+                b"PK\x03\x04" => 22,
+                b"PK\x01\x02" => 24,
+                _ => continue,
+            };
+            data[i + offset..i + offset + 4].copy_from_slice(&10u32.to_le_bytes());
+        }
+        let limits = Limits {
+            max_total_size: 1000,
+            ..Default::default()
+        };
+        let mut zip = ZipSource::new(Cursor::new(data), &limits).unwrap();
+        let mut buf = vec![];
+        zip.read("a.xhtml", &mut buf).unwrap();
+        assert_eq!(buf.len(), 800);
+        // reading an entry again doesn't count it twice
+        zip.read("a.xhtml", &mut buf).unwrap();
+        assert!(matches!(
+            zip.read("b.xhtml", &mut buf),
+            Err(EpubError::TooLarge(1000))
+        ));
+    }
+
+    #[test]
+    fn traversal_entry() {
+        let mut zip = zip::ZipWriter::new(Cursor::new(vec![]));
+        let options = zip::write::SimpleFileOptions::default();
+        zip.start_file("mimetype", options).unwrap();
+        zip.write_all(b"application/epub+zip").unwrap();
+        zip.start_file("../../evil.sh", options).unwrap();
+        zip.write_all(b"rm -rf ~").unwrap();
+        let data = zip.finish().unwrap();
+        assert!(matches!(
+            ZipSource::new(data, &Limits::default()),
+            Err(EpubError::PathTraversal(p)) if p == "../../evil.sh"
+        ));
+    }
+}
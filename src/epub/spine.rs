@@ -1,28 +1,65 @@
-use quick_xml::{events::Event, Reader};
+use quick_xml::{
+    events::{BytesStart, Event},
+    Reader,
+};
 
 use super::EpubError;
 
+/// The direction pages are turned in
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum PageProgression {
+    #[default]
+    Ltr,
+    Rtl,
+}
+
+#[derive(Debug, Clone)]
+pub struct Itemref {
+    pub idref: String,
+    /// whether the item is part of the reading order rather than only reachable by links
+    pub linear: bool,
+}
+
 #[derive(Debug, Default)]
 pub struct Spine {
-    pub items: Vec<String>,
+    pub items: Vec<Itemref>,
+    /// manifest id of the EPUB2 NCX document
+    pub toc: Option<String>,
+    pub progression: PageProgression,
 }
 impl Spine {
-    pub fn extract(reader: &mut Reader<&[u8]>) -> Result<Self, EpubError> {
+    pub fn extract(tag: &BytesStart, reader: &mut Reader<&[u8]>) -> Result<Self, EpubError> {
         let mut depth = 1;
         let mut spine = Self::default();
+        if let Some(attr) = tag.try_get_attribute("toc")? {
+            spine.toc = Some(attr.unescape_value()?.into_owned());
+        }
+        if let Some(attr) = tag.try_get_attribute("page-progression-direction")? {
+            if attr.unescape_value()?.as_ref() == "rtl" {
+                spine.progression = PageProgression::Rtl;
+            }
+        }
         loop {
             match reader.read_event() {
                 Ok(Event::Empty(ref e)) if e.name().as_ref() == b"itemref" => {
+                    let mut idref = None;
+                    let mut linear = true;
                     for attr in e.attributes() {
                         let Ok(attr) = attr else {
                             continue;
                         };
-                        if attr.key.as_ref() == b"idref" {
-                            if let Ok(val) = attr.unescape_value() {
-                                spine.items.push(val.into_owned());
-                            }
+                        let Ok(val) = attr.unescape_value() else {
+                            continue;
+                        };
+                        match attr.key.as_ref() {
+                            b"idref" => idref = Some(val.into_owned()),
+                            b"linear" => linear = val.as_ref() != "no",
+                            _ => (),
                         }
                     }
+                    if let Some(idref) = idref {
+                        spine.items.push(Itemref { idref, linear });
+                    }
                 }
                 Ok(Event::End(_)) => {
                     depth -= 1;
@@ -45,7 +82,7 @@ mod tests {
     use quick_xml::events::Event;
     use quick_xml::Reader;
 
-    use super::Spine;
+    use super::{PageProgression, Spine};
 
     #[test]
     fn happy_path() {
@@ -62,8 +99,11 @@ mod tests {
         loop {
             match reader.read_event() {
                 Ok(Event::Start(ref e)) if e.name().as_ref() == b"spine" => {
-                    let result = Spine::extract(&mut reader).unwrap();
+                    let result = Spine::extract(e, &mut reader).unwrap();
                     assert_eq!(result.items.len(), 4);
+                    assert_eq!(result.toc.as_deref(), Some("ncx"));
+                    assert_eq!(result.progression, PageProgression::Ltr);
+                    assert!(result.items.iter().all(|i| i.linear));
                 }
                 Ok(Event::Eof) => break, // exits the loop when reaching end of file
                 _ => (),
@@ -86,7 +126,7 @@ mod tests {
         loop {
             match reader.read_event() {
                 Ok(Event::Start(ref e)) if e.name().as_ref() == b"spine" => {
-                    let result = Spine::extract(&mut reader).unwrap();
+                    let result = Spine::extract(e, &mut reader).unwrap();
                     assert_eq!(result.items.len(), 3);
                 }
                 Ok(Event::Eof) => break, // exits the loop when reaching end of file
@@ -94,4 +134,30 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn non_linear_rtl() {
+        let xml = r#"
+<?xml version='1.0' encoding='utf-8'?>
+  <spine page-progression-direction="rtl">
+    <itemref idref="text1"/>
+    <itemref idref="answers" linear="no"/>
+    <itemref idref="text2" linear="yes"/>
+  </spine>
+        "#;
+        let mut reader = Reader::from_str(xml);
+        loop {
+            match reader.read_event() {
+                Ok(Event::Start(ref e)) if e.name().as_ref() == b"spine" => {
+                    let result = Spine::extract(e, &mut reader).unwrap();
+                    assert_eq!(result.progression, PageProgression::Rtl);
+                    let linear: Vec<_> = result.items.iter().map(|i| i.linear).collect();
+                    assert_eq!(linear, [true, false, true]);
+                    assert_eq!(result.items[1].idref, "answers");
+                }
+                Ok(Event::Eof) => break, // exits the loop when reaching end of file
+                _ => (),
+            }
+        }
+    }
 }
@@ -0,0 +1,457 @@
+use quick_xml::{
+    events::{BytesStart, Event},
+    Reader,
+};
+
+use crate::document::{Toc, TocEntry};
+
+use super::{html::unescape, index::Index, EpubError};
+
+/// Parse the `<nav epub:type="toc">` element of an EPUB3 navigation document. Hrefs are
+/// resolved relative to `nav_path`, the location of the navigation document in the container.
+/// Lists nested deeper than `max_depth` are an error
+pub fn from_nav(
+    xml: &str,
+    nav_path: &str,
+    index: &Index,
+    max_depth: usize,
+) -> Result<Toc, EpubError> {
+    let entries = read_nav(xml, "toc", max_depth)?
+        .into_iter()
+        .map(|p| resolve(p, nav_path, index))
+        .collect();
+    Ok(Toc::new(entries))
+}
+
+/// Parse the `<navMap>` of an EPUB2 NCX document. Hrefs are resolved relative to `ncx_path`,
+/// and nav points nested deeper than `max_depth` are an error
+pub fn from_ncx(
+    xml: &str,
+    ncx_path: &str,
+    index: &Index,
+    max_depth: usize,
+) -> Result<Toc, EpubError> {
+    let mut reader = Reader::from_str(xml);
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"navMap" => {
+                let points = extract_nav_map(&mut reader, max_depth)?;
+                let entries = points
+                    .into_iter()
+                    .map(|p| resolve(p, ncx_path, index))
+                    .collect();
+                return Ok(Toc::new(entries));
+            }
+            Ok(Event::Eof) => return Err(EpubError::UnexpectedEof),
+            Err(e) => return Err(e.into()),
+            _ => (),
+        }
+    }
+}
+
+/// Turn a nav point into a toc entry pointing at the spine item its href leads to
+fn resolve(point: NavPoint, from: &str, index: &Index) -> TocEntry {
+    let location = point.href.and_then(|href| index.resolve(from, &href));
+    let children = point
+        .children
+        .into_iter()
+        .map(|c| resolve(c, from, index))
+        .collect();
+    TocEntry::new(point.label, location, children)
+}
+
+/// A toc entry as written in the navigation document, before its href is resolved
+#[derive(Debug, Default)]
+pub(super) struct NavPoint {
+    pub label: String,
+    pub href: Option<String>,
+    /// the `epub:type` of the link, which landmarks use to tell what they point to
+    pub kind: Option<String>,
+    play_order: Option<usize>,
+    pub children: Vec<NavPoint>,
+}
+
+/// The entries of the `<nav>` with the given `epub:type` in an EPUB3 navigation document
+pub(super) fn read_nav(
+    xml: &str,
+    kind: &str,
+    max_depth: usize,
+) -> Result<Vec<NavPoint>, EpubError> {
+    let mut reader = Reader::from_str(xml);
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"nav" && is_nav(e, kind) => {
+                return extract_nav(&mut reader, max_depth);
+            }
+            Ok(Event::Eof) => return Err(EpubError::UnexpectedEof),
+            Err(e) => return Err(e.into()),
+            _ => (),
+        }
+    }
+}
+
+/// The `<pageTarget>`s of an EPUB2 NCX `<pageList>`, in play order
+pub(super) fn read_page_list(xml: &str, max_depth: usize) -> Result<Vec<NavPoint>, EpubError> {
+    let mut reader = Reader::from_str(xml);
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"pageList" => break,
+            Ok(Event::Eof) => return Err(EpubError::UnexpectedEof),
+            Err(e) => return Err(e.into()),
+            _ => (),
+        }
+    }
+    let mut points = vec![];
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"pageTarget" => {
+                points.push(extract_nav_point(e, &mut reader, 0, max_depth)?);
+            }
+            Ok(Event::Start(ref e)) => {
+                reader.read_to_end(e.name())?;
+            }
+            Ok(Event::End(_)) => break,
+            Ok(Event::Eof) => return Err(EpubError::UnexpectedEof),
+            Err(e) => return Err(e.into()),
+            _ => {}
+        }
+    }
+    sort_by_play_order(&mut points);
+    Ok(points)
+}
+
+fn is_nav(tag: &BytesStart, kind: &str) -> bool {
+    let Ok(Some(attr)) = tag.try_get_attribute("epub:type") else {
+        return false;
+    };
+    attr.unescape_value()
+        .is_ok_and(|v| v.split_whitespace().any(|t| t == kind))
+}
+
+fn extract_nav(reader: &mut Reader<&[u8]>, max_depth: usize) -> Result<Vec<NavPoint>, EpubError> {
+    let mut depth = 1;
+    let mut points = vec![];
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"ol" => {
+                points.append(&mut extract_list(reader, 0, max_depth)?);
+            }
+            Ok(Event::Start(_)) => depth += 1,
+            Ok(Event::End(_)) => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            Ok(Event::Eof) => return Err(EpubError::UnexpectedEof),
+            Err(e) => return Err(e.into()),
+            _ => {}
+        }
+    }
+    Ok(points)
+}
+
+/// The items of a list `depth` lists deep
+fn extract_list(
+    reader: &mut Reader<&[u8]>,
+    depth: usize,
+    max_depth: usize,
+) -> Result<Vec<NavPoint>, EpubError> {
+    if depth >= max_depth {
+        return Err(EpubError::TooDeep(max_depth));
+    }
+    let mut points = vec![];
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"li" => {
+                points.push(extract_item(reader, depth, max_depth)?);
+            }
+            Ok(Event::Start(ref e)) => {
+                reader.read_to_end(e.name())?;
+            }
+            Ok(Event::End(_)) => break,
+            Ok(Event::Eof) => return Err(EpubError::UnexpectedEof),
+            Err(e) => return Err(e.into()),
+            _ => {}
+        }
+    }
+    Ok(points)
+}
+
+fn extract_item(
+    reader: &mut Reader<&[u8]>,
+    depth: usize,
+    max_depth: usize,
+) -> Result<NavPoint, EpubError> {
+    let mut point = NavPoint::default();
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(ref e)) => match e.local_name().as_ref() {
+                b"a" => {
+                    if let Ok(Some(attr)) = e.try_get_attribute("href") {
+                        point.href = Some(attr.unescape_value()?.into_owned());
+                    }
+                    if let Ok(Some(attr)) = e.try_get_attribute("epub:type") {
+                        point.kind = Some(attr.unescape_value()?.into_owned());
+                    }
+                    point.label = extract_label(reader)?;
+                }
+                b"span" => point.label = extract_label(reader)?,
+                b"ol" => point.children = extract_list(reader, depth + 1, max_depth)?,
+                _ => {
+                    reader.read_to_end(e.name())?;
+                }
+            },
+            Ok(Event::End(_)) => break,
+            Ok(Event::Eof) => return Err(EpubError::UnexpectedEof),
+            Err(e) => return Err(e.into()),
+            _ => {}
+        }
+    }
+    Ok(point)
+}
+
+/// Concatenate all text inside the current element, including that of nested elements
+fn extract_label(reader: &mut Reader<&[u8]>) -> Result<String, EpubError> {
+    let mut depth = 1;
+    let mut label = String::new();
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(_)) => depth += 1,
+            Ok(Event::Text(text)) => label.push_str(&unescape(std::str::from_utf8(&text)?)),
+            Ok(Event::End(_)) => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            Ok(Event::Eof) => return Err(EpubError::UnexpectedEof),
+            Err(e) => return Err(e.into()),
+            _ => {}
+        }
+    }
+    Ok(label.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+fn extract_nav_map(
+    reader: &mut Reader<&[u8]>,
+    max_depth: usize,
+) -> Result<Vec<NavPoint>, EpubError> {
+    let mut points = vec![];
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"navPoint" => {
+                points.push(extract_nav_point(e, reader, 0, max_depth)?);
+            }
+            Ok(Event::Start(ref e)) => {
+                reader.read_to_end(e.name())?;
+            }
+            Ok(Event::End(_)) => break,
+            Ok(Event::Eof) => return Err(EpubError::UnexpectedEof),
+            Err(e) => return Err(e.into()),
+            _ => {}
+        }
+    }
+    sort_by_play_order(&mut points);
+    Ok(points)
+}
+
+/// A nav point and those nested in it, `depth` nav points deep
+fn extract_nav_point(
+    tag: &BytesStart,
+    reader: &mut Reader<&[u8]>,
+    depth: usize,
+    max_depth: usize,
+) -> Result<NavPoint, EpubError> {
+    if depth >= max_depth {
+        return Err(EpubError::TooDeep(max_depth));
+    }
+    let mut point = NavPoint::default();
+    if let Ok(Some(attr)) = tag.try_get_attribute("playOrder") {
+        point.play_order = attr.unescape_value()?.trim().parse().ok();
+    }
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(ref e)) => match e.local_name().as_ref() {
+                b"navLabel" => point.label = extract_label(reader)?,
+                b"navPoint" => {
+                    let child = extract_nav_point(e, reader, depth + 1, max_depth)?;
+                    point.children.push(child);
+                }
+                b"content" => {
+                    point.href = content_src(e)?;
+                    reader.read_to_end(e.name())?;
+                }
+                _ => {
+                    reader.read_to_end(e.name())?;
+                }
+            },
+            Ok(Event::Empty(ref e)) if e.local_name().as_ref() == b"content" => {
+                point.href = content_src(e)?;
+            }
+            Ok(Event::End(_)) => break,
+            Ok(Event::Eof) => return Err(EpubError::UnexpectedEof),
+            Err(e) => return Err(e.into()),
+            _ => {}
+        }
+    }
+    sort_by_play_order(&mut point.children);
+    Ok(point)
+}
+
+fn content_src(tag: &BytesStart) -> Result<Option<String>, EpubError> {
+    match tag.try_get_attribute("src")? {
+        Some(attr) => Ok(Some(attr.unescape_value()?.into_owned())),
+        None => Ok(None),
+    }
+}
+
+/// Siblings without a `playOrder` keep their document position relative to each other
+fn sort_by_play_order(points: &mut [NavPoint]) {
+    if points.iter().all(|p| p.play_order.is_some()) {
+        points.sort_by_key(|p| p.play_order);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quick_xml::Reader;
+
+    use crate::epub::{
+        index::Index,
+        manifest::Manifest,
+        spine::{Itemref, Spine},
+        EpubError,
+    };
+
+    use super::{from_nav, from_ncx};
+
+    fn index() -> Index {
+        let xml = r#"<manifest>
+    <item id="ch1" href="ch1.xhtml" media-type="application/xhtml+xml"/>
+    <item id="ch2" href="ch2.xhtml" media-type="application/xhtml+xml"/>
+    <item id="title" href="text/title.html" media-type="application/xhtml+xml"/>
+    <item id="text1" href="text/ch1.html" media-type="application/xhtml+xml"/>
+  </manifest>
+        "#;
+        let mut reader = Reader::from_str(xml);
+        let _ = reader.read_event();
+        let manifest = Manifest::extract(&mut reader).unwrap();
+        let spine = Spine {
+            items: ["ch1", "ch2", "title", "text1"]
+                .map(|id| Itemref {
+                    idref: id.to_owned(),
+                    linear: true,
+                })
+                .to_vec(),
+            ..Default::default()
+        };
+        Index::new(&manifest, spine, "OEBPS/content.opf")
+    }
+
+    #[test]
+    fn nested_nav() {
+        let xml = r#"
+<?xml version='1.0' encoding='utf-8'?>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+  <body>
+    <nav epub:type="landmarks"><ol><li><a href="cover.xhtml">Cover</a></li></ol></nav>
+    <nav epub:type="toc" id="toc">
+      <h1>Contents</h1>
+      <ol>
+        <li><a href="ch1.xhtml">Chapter <em>One</em></a></li>
+        <li>
+          <span>Part Two</span>
+          <ol>
+            <li><a href="ch2.xhtml#s1">Chapter Two</a></li>
+            <li><a href="ch3.xhtml">Chapter Three</a></li>
+          </ol>
+        </li>
+      </ol>
+    </nav>
+  </body>
+</html>
+        "#;
+        let toc = from_nav(xml, "OEBPS/nav.xhtml", &index(), 256).unwrap();
+        assert_eq!(toc.entries().len(), 2);
+        assert_eq!(toc.entries()[0].label(), "Chapter One");
+        assert_eq!(toc.entries()[1].label(), "Part Two");
+        assert_eq!(toc.entries()[1].children().len(), 2);
+        let part_two = &toc.entries()[1];
+        assert!(part_two.location().is_none());
+        let chapter_two = part_two.children()[0].location().unwrap();
+        assert_eq!(chapter_two.id(), "ch2");
+        assert_eq!(chapter_two.fragment(), Some("s1"));
+        // not in the spine
+        assert!(part_two.children()[1].location().is_none());
+
+        let labels: Vec<_> = toc.iter().map(|e| e.label()).collect();
+        assert_eq!(
+            labels,
+            ["Chapter One", "Part Two", "Chapter Two", "Chapter Three"]
+        );
+    }
+
+    #[test]
+    fn ncx_nav_map() {
+        let xml = r#"
+<?xml version='1.0' encoding='UTF-8'?>
+<ncx xmlns="http://www.daisy.org/z3986/2005/ncx/" version="2005-1">
+  <docTitle><text>Pride and Prejudice</text></docTitle>
+  <navMap>
+    <navPoint id="np-2" playOrder="21">
+      <navLabel><text>Chapter I.</text></navLabel>
+      <content src="text/ch1.html#pgepubid00022"/>
+      <navPoint id="np-3" playOrder="23">
+        <navLabel><text>Letter</text></navLabel>
+        <content src="text/ch1.html#pgepubid00024"/>
+      </navPoint>
+    </navPoint>
+    <navPoint id="np-1" playOrder="1">
+      <navLabel><text>PRIDE. and PREJUDICE</text></navLabel>
+      <content src="text/title.html"/>
+    </navPoint>
+  </navMap>
+</ncx>
+        "#;
+        let toc = from_ncx(xml, "OEBPS/toc.ncx", &index(), 256).unwrap();
+        assert_eq!(toc.entries().len(), 2);
+        assert_eq!(toc.entries()[0].label(), "PRIDE. and PREJUDICE");
+        let title = toc.entries()[0].location().unwrap();
+        assert_eq!(title.id(), "title");
+        assert_eq!(title.fragment(), None);
+        assert_eq!(toc.entries()[1].children()[0].label(), "Letter");
+        let letter = toc.entries()[1].children()[0].location().unwrap();
+        assert_eq!(letter.id(), "text1");
+        assert_eq!(letter.fragment(), Some("pgepubid00024"));
+    }
+
+    #[test]
+    fn missing_toc_nav() {
+        let xml = r#"<html><body><nav epub:type="landmarks"><ol></ol></nav></body></html>"#;
+        let toc = from_nav(xml, "nav.xhtml", &Index::default(), 256);
+        assert!(toc.is_err());
+    }
+
+    #[test]
+    fn too_deep() {
+        let nav = format!(
+            r#"<nav epub:type="toc">{}<a href="ch1.xhtml">Deep</a>{}</nav>"#,
+            "<ol><li>".repeat(20),
+            "</li></ol>".repeat(20)
+        );
+        let toc = from_nav(&nav, "OEBPS/nav.xhtml", &index(), 16);
+        assert!(matches!(toc, Err(EpubError::TooDeep(16))));
+        assert!(from_nav(&nav, "OEBPS/nav.xhtml", &index(), 32).is_ok());
+
+        let ncx = format!(
+            "<ncx><navMap>{}{}</navMap></ncx>",
+            r#"<navPoint><navLabel><text>Deep</text></navLabel><content src="ch1.xhtml"/>"#
+                .repeat(20),
+            "</navPoint>".repeat(20)
+        );
+        let toc = from_ncx(&ncx, "OEBPS/toc.ncx", &index(), 16);
+        assert!(matches!(toc, Err(EpubError::TooDeep(16))));
+        assert!(from_ncx(&ncx, "OEBPS/toc.ncx", &index(), 32).is_ok());
+    }
+}
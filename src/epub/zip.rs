@@ -1,39 +1,94 @@
-use std::{fs::File, io::Read, path::PathBuf};
+use std::{
+    collections::HashSet,
+    fmt::Debug,
+    io::{Read, Seek},
+};
 
-use quick_xml::{events::Event, Reader};
-use zip::ZipArchive;
+use zip::{result::ZipError, ZipArchive};
 
-use super::EpubError;
+use super::{
+    limits::Limits,
+    source::{is_contained, read_limited, Source},
+    EpubError,
+};
 
-pub fn find_rootfile(xml: &str) -> Result<PathBuf, EpubError> {
-    let mut reader = Reader::from_str(xml);
-    let mut buf = vec![];
-    loop {
-        let a = reader.read_event_into(&mut buf)?;
-        match a {
-            Event::Empty(ref e) if e.name().as_ref() == b"rootfile" => {
-                let Ok(Some(attr)) = e.try_get_attribute("full-path") else {
-                    return Err(EpubError::Rootfile);
-                };
-                return std::str::from_utf8(&attr.value)
-                    .map(PathBuf::from)
-                    .map_err(Into::into);
+/// A packed book, read from a file or from memory
+pub struct ZipSource<R> {
+    archive: ZipArchive<R>,
+    max_entry_size: u64,
+    max_total_size: u64,
+    /// entries that have been read, each counted once towards `total_read`
+    read: HashSet<String>,
+    /// bytes actually unpacked, which can be more than the headers claim
+    total_read: u64,
+}
+impl<R: Read + Seek> ZipSource<R> {
+    /// Open the archive, rejecting it if it breaks the limits or holds entries whose names
+    /// would leave the container when unpacked
+    pub fn new(reader: R, limits: &Limits) -> Result<Self, EpubError> {
+        let mut archive = ZipArchive::new(reader)?;
+        if archive.len() > limits.max_entries {
+            return Err(EpubError::TooManyEntries(limits.max_entries));
+        }
+        let mut total_size: u64 = 0;
+        for i in 0..archive.len() {
+            let entry = archive.by_index_raw(i)?;
+            if !is_contained(entry.name()) {
+                return Err(EpubError::PathTraversal(entry.name().to_owned()));
+            }
+            // sizes in the headers may lie, so `read` checks what it actually unpacks too
+            total_size = total_size.saturating_add(entry.size());
+            if total_size > limits.max_total_size {
+                return Err(EpubError::TooLarge(limits.max_total_size));
             }
-            Event::Eof => break,
-            _ => (),
         }
+        Ok(Self {
+            archive,
+            max_entry_size: limits.max_entry_size,
+            max_total_size: limits.max_total_size,
+            read: HashSet::new(),
+            total_read: 0,
+        })
+    }
+}
+impl<R: Read + Seek> Debug for ZipSource<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ZipSource")
+            .field("entries", &self.archive.len())
+            .field("max_entry_size", &self.max_entry_size)
+            .field("total_read", &self.total_read)
+            .finish()
     }
-    // the loop ended without finding the rootfile path
-    Err(EpubError::UnexpectedEof)
 }
+impl<R: Read + Seek> Source for ZipSource<R> {
+    fn read(&mut self, path: &str, buf: &mut Vec<u8>) -> Result<(), EpubError> {
+        let z = match self.archive.by_name(path) {
+            Ok(z) => z,
+            Err(ZipError::FileNotFound) => return Err(EpubError::MissingResource(path.to_owned())),
+            Err(e) => return Err(e.into()),
+        };
+        if z.size() > self.max_entry_size {
+            return Err(EpubError::EntryTooLarge(
+                path.to_owned(),
+                self.max_entry_size,
+            ));
+        }
+        read_limited(z, path, self.max_entry_size, buf)?;
+        if self.read.insert(path.to_owned()) {
+            self.total_read = self.total_read.saturating_add(buf.len() as u64);
+        }
+        if self.total_read > self.max_total_size {
+            buf.clear();
+            return Err(EpubError::TooLarge(self.max_total_size));
+        }
+        Ok(())
+    }
 
-pub fn read_document(
-    sourcefile: &mut ZipArchive<File>,
-    id: &str,
-    buf: &mut Vec<u8>,
-) -> Result<(), EpubError> {
-    buf.clear();
-    let mut z = sourcefile.by_name(id)?;
-    let _ = z.read_to_end(buf)?;
-    Ok(())
+    fn files(&self) -> Vec<String> {
+        self.archive
+            .file_names()
+            .filter(|name| !name.ends_with('/'))
+            .map(ToOwned::to_owned)
+            .collect()
+    }
 }
@@ -0,0 +1,7 @@
+mod body;
+mod book;
+mod error;
+mod metadata;
+
+pub use book::Book;
+pub use error::Fb2Error;
@@ -0,0 +1,119 @@
+use quick_xml::{
+    events::{BytesStart, Event},
+    Reader,
+};
+
+use crate::epub::{Element, ElementVariant, Node};
+
+use super::{metadata::href, Fb2Error};
+
+/// Which html element a FictionBook element is laid out as. Paragraphs inside titles
+/// become headings, since that's what titles are made of
+fn variant(name: &str, in_title: bool) -> ElementVariant {
+    match name {
+        "body" => ElementVariant::Body,
+        "section" => ElementVariant::Section,
+        "p" if in_title => ElementVariant::H2,
+        "p" | "v" | "text-author" | "date" => ElementVariant::P,
+        "subtitle" => ElementVariant::H3,
+        "title" | "poem" | "stanza" => ElementVariant::Div,
+        "epigraph" | "cite" => ElementVariant::Blockquote,
+        "emphasis" => ElementVariant::I,
+        "strong" => ElementVariant::B,
+        "empty-line" => ElementVariant::Br,
+        "image" => ElementVariant::Image,
+        "a" => ElementVariant::A,
+        "tr" => ElementVariant::Tr,
+        _ => ElementVariant::Ignored,
+    }
+}
+
+/// An element whose children are still being read
+struct Open {
+    name: String,
+    variant: ElementVariant,
+    attributes: Vec<(String, String)>,
+    children: Vec<Node>,
+}
+impl Open {
+    /// Map the element that has just started onto an html one, keeping the attributes that
+    /// matter for layout and links. Sections are given an id if they have none, so the toc
+    /// can point at them
+    fn new(tag: &BytesStart, in_title: bool, sections: &mut usize) -> Result<Self, Fb2Error> {
+        let name = std::str::from_utf8(tag.local_name().into_inner())
+            .unwrap_or_default()
+            .to_lowercase();
+        let mut attributes = vec![];
+        if let Some(id) = tag.try_get_attribute("id")? {
+            attributes.push(("id".to_owned(), id.unescape_value()?.into_owned()));
+        } else if name == "section" {
+            attributes.push(("id".to_owned(), format!("section-{}", sections)));
+        }
+        if name == "section" {
+            *sections += 1;
+        }
+        if let Some(href) = href(tag)? {
+            attributes.push(("href".to_owned(), href));
+        }
+        if let Some(kind) = tag.try_get_attribute("type")? {
+            if name == "a" && kind.unescape_value()? == "note" {
+                attributes.push(("epub:type".to_owned(), "noteref".to_owned()));
+            }
+        }
+        Ok(Self {
+            variant: variant(&name, in_title),
+            name,
+            attributes,
+            children: vec![],
+        })
+    }
+
+    fn close(self) -> Node {
+        let element = Element::build(&self.name, self.variant, self.attributes, self.children);
+        Node::Element(element)
+    }
+}
+
+/// Build the tree for the `<body>` that has just started. `sections` counts the sections
+/// seen so far in the document, for numbering the ones without an id
+pub fn extract(
+    tag: &BytesStart,
+    reader: &mut Reader<&[u8]>,
+    max_depth: usize,
+    sections: &mut usize,
+) -> Result<Node, Fb2Error> {
+    let mut stack = vec![Open::new(tag, false, sections)?];
+    loop {
+        let in_title = stack.iter().any(|e| e.name == "title");
+        let top = stack.len() - 1;
+        match reader.read_event() {
+            Ok(Event::Start(ref e)) => {
+                if stack.len() >= max_depth {
+                    return Err(Fb2Error::TooDeep(max_depth));
+                }
+                stack.push(Open::new(e, in_title, sections)?);
+            }
+            Ok(Event::Empty(ref e)) => {
+                let el = Open::new(e, in_title, sections)?;
+                stack[top].children.push(el.close());
+            }
+            Ok(Event::Text(text)) => {
+                let text = text.unescape()?;
+                let text: String = text.split_ascii_whitespace().collect::<Vec<_>>().join(" ");
+                if !text.is_empty() {
+                    stack[top].children.push(Node::Text(text));
+                }
+            }
+            Ok(Event::End(_)) => {
+                let el = stack.remove(top);
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(el.close()),
+                    None => return Ok(el.close()),
+                }
+            }
+            Ok(Event::Eof) => return Err(Fb2Error::UnexpectedEof),
+            Err(e) => return Err(e.into()),
+            _ => {}
+        }
+    }
+}
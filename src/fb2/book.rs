@@ -0,0 +1,438 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{Read, Seek},
+    path::Path,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use quick_xml::{events::Event, Reader};
+
+use crate::document::{Details, Document, DocumentError, Location, Section, Toc, TocEntry};
+use crate::draw::Image;
+use crate::epub::{decode, Element, ElementVariant, EpubError, Limits, Node, Source, ZipSource};
+
+use super::{body, metadata::Metadata, Fb2Error};
+
+/// A FictionBook document. Each top-level section of the main body is a section of the
+/// document, and each body of notes is one more, reached only through links
+#[derive(Debug, Default)]
+pub struct Book {
+    metadata: Metadata,
+    sections: Vec<Section>,
+    /// the section each element id is in
+    ids: HashMap<String, usize>,
+    toc: Toc,
+    /// base64 encoded contents of the `<binary>` elements, by id
+    binaries: HashMap<String, String>,
+}
+
+impl Book {
+    /// Open an `.fb2` file, or an `.fb2.zip` archive holding one
+    pub fn new<P: AsRef<Path>>(path: &P, limits: Limits) -> Result<Self, Fb2Error> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        if path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("zip"))
+        {
+            return Self::from_zip(file, limits);
+        }
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if file.metadata()?.len() > limits.max_entry_size {
+            let error = EpubError::EntryTooLarge(name.into_owned(), limits.max_entry_size);
+            return Err(error.into());
+        }
+        let mut bytes = vec![];
+        file.take(limits.max_entry_size).read_to_end(&mut bytes)?;
+        Self::from_bytes(&bytes, &name, limits)
+    }
+
+    /// Open the first `.fb2` file in a zip archive
+    pub fn from_zip<R>(reader: R, limits: Limits) -> Result<Self, Fb2Error>
+    where
+        R: Read + Seek,
+    {
+        let mut archive = ZipSource::new(reader, &limits)?;
+        let name = archive
+            .files()
+            .into_iter()
+            .find(|f| f.to_lowercase().ends_with(".fb2"))
+            .ok_or(Fb2Error::NoDocument)?;
+        let mut bytes = vec![];
+        archive.read(&name, &mut bytes)?;
+        Self::from_bytes(&bytes, &name, limits)
+    }
+
+    /// Parse a FictionBook document. `path` is where it was read from, which section
+    /// locations carry
+    pub fn from_bytes(bytes: &[u8], path: &str, limits: Limits) -> Result<Self, Fb2Error> {
+        let xml = decode(bytes)?;
+        let mut reader = Reader::from_str(&xml);
+        let mut book = Self::default();
+        let mut bodies = vec![];
+        let mut section_count = 0;
+        loop {
+            match reader.read_event() {
+                Ok(Event::Start(ref e)) => match e.local_name().as_ref() {
+                    b"title-info" => book.metadata = Metadata::extract(&mut reader)?,
+                    b"body" => {
+                        let notes = e.try_get_attribute("name")?.is_some();
+                        let body =
+                            body::extract(e, &mut reader, limits.max_depth, &mut section_count)?;
+                        bodies.push((body, notes));
+                    }
+                    b"binary" => {
+                        let id = e.try_get_attribute("id")?;
+                        let data = reader.read_text(e.name())?;
+                        if let Some(id) = id {
+                            let id = id.unescape_value()?.into_owned();
+                            book.binaries.insert(id, data.into_owned());
+                        }
+                    }
+                    _ => (),
+                },
+                Ok(Event::Eof) => break,
+                Err(e) => return Err(e.into()),
+                _ => (),
+            }
+        }
+        if bodies.is_empty() {
+            return Err(Fb2Error::NoBody);
+        }
+
+        let mut entries = vec![];
+        for (body, notes) in bodies {
+            let Node::Element(body) = body else {
+                continue;
+            };
+            if notes {
+                let location = Location::new(
+                    format!("notes-{}", book.sections.len()),
+                    path.to_owned(),
+                    false,
+                );
+                book.push(location, Node::Element(body));
+                continue;
+            }
+            // whatever comes before the first section, such as the book's title
+            let (front, sections): (Vec<_>, Vec<_>) = body
+                .children()
+                .iter()
+                .cloned()
+                .partition(|n| !is_section(n));
+            if !front.is_empty() {
+                let front = Element::build("section", ElementVariant::Section, vec![], front);
+                let location = Location::new("front".to_owned(), path.to_owned(), true);
+                book.push(location, Node::Element(front));
+            }
+            for section in sections {
+                let id = section.element().and_then(|e| e.id()).unwrap_or_default();
+                let location = Location::new(id.to_owned(), path.to_owned(), true);
+                entries.extend(toc_entries(&section, &location));
+                book.push(location, section);
+            }
+        }
+        book.toc = Toc::new(entries);
+        Ok(book)
+    }
+
+    fn push(&mut self, location: Location, node: Node) {
+        let index = self.sections.len();
+        for id in node.iter().filter_map(|n| n.element()?.id()) {
+            self.ids.insert(id.to_owned(), index);
+        }
+        self.sections.push(Section { location, node });
+    }
+
+    fn position_of(&self, id: &str) -> Result<usize, DocumentError> {
+        self.sections
+            .iter()
+            .position(|s| s.location.id() == id)
+            .ok_or(DocumentError::SectionNotFound(id.to_owned()))
+    }
+
+    /// A copy of the section at the given position, for laying out
+    fn section_at(&self, position: usize, fragment: Option<String>) -> Section {
+        let section = &self.sections[position];
+        Section {
+            location: section.location.with_fragment(fragment),
+            node: section.node.clone(),
+        }
+    }
+
+    /// Decode the image in the `<binary>` an href like `#cover.jpg` points to
+    fn decode_image(&self, href: &str) -> Result<Image, Fb2Error> {
+        let id = href.trim_start_matches('#');
+        let data = self
+            .binaries
+            .get(id)
+            .ok_or_else(|| Fb2Error::MissingBinary(id.to_owned()))?;
+        let data: String = data.split_ascii_whitespace().collect();
+        let bytes = STANDARD
+            .decode(data)
+            .map_err(|e| Fb2Error::Base64(id.to_owned(), e))?;
+        Ok(Image::from_bytes(&bytes)?)
+    }
+}
+
+fn is_section(node: &Node) -> bool {
+    node.element()
+        .is_some_and(|e| e.variant() == ElementVariant::Section)
+}
+
+/// The toc entries for a section and the ones nested in it. Sections without a title are
+/// left out, but the ones nested in them aren't
+fn toc_entries(node: &Node, location: &Location) -> Vec<TocEntry> {
+    let Some(el) = node.element().filter(|_| is_section(node)) else {
+        return vec![];
+    };
+    let children: Vec<_> = el
+        .children()
+        .iter()
+        .flat_map(|c| toc_entries(c, location))
+        .collect();
+    let title = el
+        .children()
+        .iter()
+        .find(|c| c.element().is_some_and(|e| e.name() == "title"));
+    let Some(title) = title else {
+        return children;
+    };
+    let label: Vec<_> = title.iter().filter_map(Node::text).collect();
+    let fragment = el.id().filter(|id| *id != location.id());
+    let location = location.with_fragment(fragment.map(str::to_owned));
+    vec![TocEntry::new(label.join(" "), Some(location), children)]
+}
+
+impl Document for Book {
+    fn title(&self) -> Option<&str> {
+        self.metadata.title()
+    }
+
+    fn authors(&self) -> Vec<&str> {
+        self.metadata.authors().iter().map(String::as_str).collect()
+    }
+
+    fn details(&self) -> Details<'_> {
+        let metadata = &self.metadata;
+        Details {
+            languages: metadata.language().into_iter().collect(),
+            date: metadata.date(),
+            subjects: metadata.genres().iter().map(String::as_str).collect(),
+            series: metadata.series(),
+            description: metadata.annotation(),
+            ..Default::default()
+        }
+    }
+
+    fn first(&mut self) -> Result<Section, DocumentError> {
+        let position = self
+            .sections
+            .iter()
+            .position(|s| s.location.is_linear())
+            .ok_or(DocumentError::SectionNotFound("[FIRST]".to_owned()))?;
+        Ok(self.section_at(position, None))
+    }
+
+    fn next(&mut self, id: &str) -> Result<Section, DocumentError> {
+        let current = self.position_of(id)?;
+        let position = (current + 1..self.sections.len())
+            .find(|i| self.sections[*i].location.is_linear())
+            .ok_or(DocumentError::SectionNotFound(id.to_owned()))?;
+        Ok(self.section_at(position, None))
+    }
+
+    fn prev(&mut self, id: &str) -> Result<Section, DocumentError> {
+        let current = self.position_of(id)?;
+        let position = (0..current)
+            .rev()
+            .find(|i| self.sections[*i].location.is_linear())
+            .ok_or(DocumentError::SectionNotFound(id.to_owned()))?;
+        Ok(self.section_at(position, None))
+    }
+
+    fn section(&mut self, location: &Location) -> Result<Section, DocumentError> {
+        let position = self.position_of(location.id())?;
+        Ok(self.section_at(position, location.fragment().map(str::to_owned)))
+    }
+
+    fn position(&self, id: &str) -> Option<usize> {
+        self.position_of(id).ok()
+    }
+
+    /// Links inside the document are all to element ids, as in `#note1`
+    fn resolve_link(&self, _from: &Location, href: &str) -> Option<Location> {
+        let id = href.strip_prefix('#')?;
+        let position = *self.ids.get(id)?;
+        let location = &self.sections[position].location;
+        Some(location.with_fragment(Some(id.to_owned())))
+    }
+
+    fn image(&mut self, _from: &str, href: &str) -> Result<Image, DocumentError> {
+        Ok(self.decode_image(href)?)
+    }
+
+    fn toc(&self) -> &Toc {
+        &self.toc
+    }
+
+    fn cover(&mut self) -> Result<Option<Image>, DocumentError> {
+        let Some(href) = self.metadata.cover() else {
+            return Ok(None);
+        };
+        Ok(Some(self.decode_image(href)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use crate::document::Document;
+    use crate::epub::{ElementVariant, Limits, Node};
+
+    use super::Book;
+
+    const FB2: &str = r##"<?xml version="1.0" encoding="utf-8"?>
+<FictionBook xmlns="http://www.gribuser.ru/xml/fictionbook/2.0" xmlns:l="http://www.w3.org/1999/xlink">
+  <description>
+    <title-info>
+      <author><first-name>Антон</first-name><last-name>Чехов</last-name></author>
+      <book-title>Рассказы</book-title>
+      <coverpage><image l:href="#cover.png"/></coverpage>
+      <lang>ru</lang>
+    </title-info>
+    <document-info><author><nickname>scanner</nickname></author></document-info>
+  </description>
+  <body>
+    <title><p>Антон Чехов</p><p>Рассказы</p></title>
+    <epigraph><p>Краткость — сестра таланта.</p><text-author>А. Ч.</text-author></epigraph>
+    <section id="s1">
+      <title><p>Толстый и тонкий</p></title>
+      <p>На вокзале <emphasis>Николаевской</emphasis> железной дороги<a l:href="#n1" type="note">1</a>.</p>
+      <empty-line/>
+      <image l:href="#cover.png"/>
+    </section>
+    <section>
+      <title><p>Часть вторая</p></title>
+      <section><title><p>Глава 1</p></title><p>Текст</p></section>
+      <section><p>Без заглавия</p>
+        <section><title><p>Вложенная</p></title><poem><stanza><v>Строка</v></stanza></poem></section>
+      </section>
+    </section>
+  </body>
+  <body name="notes">
+    <section id="n1"><title><p>1</p></title><p>Примечание.</p></section>
+  </body>
+  <binary id="cover.png" content-type="image/png">
+    iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAIAAACQd1PeAAAADElEQVR4nGP4z8AAAAMBAQDJ
+    /pLvAAAAAElFTkSuQmCC
+  </binary>
+</FictionBook>"##;
+
+    #[test]
+    fn sections_and_toc() {
+        let mut book = Book::from_bytes(FB2.as_bytes(), "book.fb2", Limits::default()).unwrap();
+        assert_eq!(book.title(), Some("Рассказы"));
+        assert_eq!(book.authors(), ["Антон Чехов"]);
+        assert_eq!(book.details().languages, ["ru"]);
+
+        let front = book.first().unwrap();
+        assert_eq!(front.location.id(), "front");
+        let headings: Vec<_> = front
+            .node
+            .iter()
+            .filter(|n| {
+                n.element()
+                    .is_some_and(|e| e.variant() == ElementVariant::H2)
+            })
+            .collect();
+        assert_eq!(headings.len(), 2);
+
+        let first = book.next("front").unwrap();
+        assert_eq!(first.location.id(), "s1");
+        let variants: Vec<_> = first
+            .node
+            .iter()
+            .filter_map(|n| Some(n.element()?.variant()))
+            .collect();
+        assert!(variants.contains(&ElementVariant::I));
+        assert!(variants.contains(&ElementVariant::Br));
+        assert_eq!(first.node.first_image(), Some(("#cover.png", true)));
+
+        // notes are only reached through links
+        let second = book.next("s1").unwrap();
+        assert!(book.next(second.location.id()).is_err());
+
+        let labels: Vec<_> = book.toc().iter().map(|e| e.label()).collect();
+        assert_eq!(
+            labels,
+            ["Толстый и тонкий", "Часть вторая", "Глава 1", "Вложенная"]
+        );
+        let nested = book
+            .toc()
+            .iter()
+            .last()
+            .unwrap()
+            .location()
+            .unwrap()
+            .clone();
+        assert_eq!(nested.id(), second.location.id());
+        let section = book.section(&nested).unwrap();
+        let fragment = nested.fragment().unwrap();
+        assert!(section.node.find_by_id(fragment).is_some());
+    }
+
+    #[test]
+    fn notes_and_binaries() {
+        let mut book = Book::from_bytes(FB2.as_bytes(), "book.fb2", Limits::default()).unwrap();
+        let first = book.next("front").unwrap();
+        let link = first
+            .node
+            .iter()
+            .filter_map(Node::element)
+            .find(|e| e.is_noteref())
+            .unwrap();
+        let href = link.attribute("href").unwrap().value();
+        let note = book.resolve_link(&first.location, href).unwrap();
+        assert_eq!(note.fragment(), Some("n1"));
+        assert!(!note.is_linear());
+        let notes = book.section(&note).unwrap();
+        assert!(notes.node.find_by_id("n1").is_some());
+
+        let cover = book.cover().unwrap().unwrap();
+        assert_eq!((cover.size.width, cover.size.height), (1, 1));
+        assert!(book.image("book.fb2", "#missing.png").is_err());
+    }
+
+    #[test]
+    fn zipped() {
+        let mut zip = zip::ZipWriter::new(Cursor::new(vec![]));
+        let options = zip::write::SimpleFileOptions::default();
+        zip.start_file("Chekhov.fb2", options).unwrap();
+        zip.write_all(FB2.as_bytes()).unwrap();
+        let data = zip.finish().unwrap();
+        let book = Book::from_zip(data, Limits::default()).unwrap();
+        assert_eq!(book.title(), Some("Рассказы"));
+
+        let mut zip = zip::ZipWriter::new(Cursor::new(vec![]));
+        zip.start_file("readme.txt", options).unwrap();
+        let data = zip.finish().unwrap();
+        assert!(Book::from_zip(data, Limits::default()).is_err());
+    }
+
+    #[test]
+    fn too_deep() {
+        let limits = Limits {
+            max_depth: 16,
+            ..Default::default()
+        };
+        let nested = format!(
+            "<FictionBook><body>{}<p>deep</p>{}</body></FictionBook>",
+            "<section>".repeat(20),
+            "</section>".repeat(20)
+        );
+        assert!(Book::from_bytes(nested.as_bytes(), "deep.fb2", limits).is_err());
+    }
+}
@@ -0,0 +1,39 @@
+use thiserror::Error;
+
+use crate::epub::EpubError;
+
+#[derive(Debug, Error)]
+pub enum Fb2Error {
+    #[error("file io")]
+    FileIO(#[from] std::io::Error),
+
+    #[error("reading the container")]
+    Container(#[from] EpubError),
+
+    #[error("xml")]
+    Xml(#[from] quick_xml::Error),
+
+    #[error("xml attribute")]
+    XmlAttribute(#[from] quick_xml::events::attributes::AttrError),
+
+    #[error("xml file ended prematurely")]
+    UnexpectedEof,
+
+    #[error("markup nested deeper than {0} levels")]
+    TooDeep(usize),
+
+    #[error("no fb2 file in the archive")]
+    NoDocument,
+
+    #[error("document has no body")]
+    NoBody,
+
+    #[error("binary missing from the document: {0}")]
+    MissingBinary(String),
+
+    #[error("binary {0} isn't valid base64")]
+    Base64(String, #[source] base64::DecodeError),
+
+    #[error("image")]
+    Image(#[from] crate::draw::Error),
+}
@@ -0,0 +1,223 @@
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+use super::Fb2Error;
+
+/// What `<title-info>` says about the book
+#[derive(Debug, Default)]
+pub struct Metadata {
+    title: Option<String>,
+    authors: Vec<String>,
+    genres: Vec<String>,
+    language: Option<String>,
+    annotation: Option<String>,
+    date: Option<String>,
+    series: Option<String>,
+    series_index: Option<f32>,
+    cover: Option<String>,
+}
+impl Metadata {
+    pub fn extract(reader: &mut Reader<&[u8]>) -> Result<Self, Fb2Error> {
+        let mut metadata = Self::default();
+        loop {
+            match reader.read_event() {
+                Ok(Event::Start(ref e)) => match e.local_name().as_ref() {
+                    b"book-title" => metadata.title = Some(read_text(reader)?),
+                    b"author" => {
+                        let author = extract_author(reader)?;
+                        if !author.is_empty() {
+                            metadata.authors.push(author);
+                        }
+                    }
+                    b"genre" => metadata.genres.push(read_text(reader)?),
+                    b"lang" => metadata.language = Some(read_text(reader)?),
+                    b"annotation" => metadata.annotation = Some(read_text(reader)?),
+                    b"date" => metadata.date = Some(read_text(reader)?),
+                    b"coverpage" => metadata.cover = extract_cover(reader)?,
+                    b"sequence" => {
+                        metadata.read_sequence(e)?;
+                        reader.read_to_end(e.name())?;
+                    }
+                    _ => {
+                        reader.read_to_end(e.name())?;
+                    }
+                },
+                Ok(Event::Empty(ref e)) if e.local_name().as_ref() == b"sequence" => {
+                    metadata.read_sequence(e)?;
+                }
+                Ok(Event::End(_)) => break,
+                Ok(Event::Eof) => return Err(Fb2Error::UnexpectedEof),
+                Err(e) => return Err(e.into()),
+                _ => {}
+            }
+        }
+        Ok(metadata)
+    }
+
+    /// Only the first `<sequence>` is kept, later ones are usually broader series
+    fn read_sequence(&mut self, tag: &BytesStart) -> Result<(), Fb2Error> {
+        if self.series.is_some() {
+            return Ok(());
+        }
+        if let Some(name) = tag.try_get_attribute("name")? {
+            self.series = Some(name.unescape_value()?.into_owned());
+        }
+        if let Some(number) = tag.try_get_attribute("number")? {
+            self.series_index = number.unescape_value()?.trim().parse().ok();
+        }
+        Ok(())
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+    pub fn authors(&self) -> &[String] {
+        &self.authors
+    }
+    pub fn genres(&self) -> &[String] {
+        &self.genres
+    }
+    pub fn language(&self) -> Option<&str> {
+        self.language.as_deref()
+    }
+    pub fn annotation(&self) -> Option<&str> {
+        self.annotation.as_deref()
+    }
+    pub fn date(&self) -> Option<&str> {
+        self.date.as_deref()
+    }
+    pub fn series(&self) -> Option<(&str, Option<f32>)> {
+        self.series.as_deref().map(|s| (s, self.series_index))
+    }
+    /// The href of the cover image, pointing at a `<binary>`
+    pub fn cover(&self) -> Option<&str> {
+        self.cover.as_deref()
+    }
+}
+
+/// The author's name in reading order, or their nickname if that's all there is
+fn extract_author(reader: &mut Reader<&[u8]>) -> Result<String, Fb2Error> {
+    let (mut names, mut nickname) = (vec![], None);
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(ref e)) => match e.local_name().as_ref() {
+                b"first-name" | b"middle-name" | b"last-name" => {
+                    let name = read_text(reader)?;
+                    if !name.is_empty() {
+                        names.push(name);
+                    }
+                }
+                b"nickname" => nickname = Some(read_text(reader)?),
+                _ => {
+                    reader.read_to_end(e.name())?;
+                }
+            },
+            Ok(Event::End(_)) => break,
+            Ok(Event::Eof) => return Err(Fb2Error::UnexpectedEof),
+            Err(e) => return Err(e.into()),
+            _ => {}
+        }
+    }
+    match nickname {
+        Some(nickname) if names.is_empty() => Ok(nickname),
+        _ => Ok(names.join(" ")),
+    }
+}
+
+fn extract_cover(reader: &mut Reader<&[u8]>) -> Result<Option<String>, Fb2Error> {
+    let mut cover = None;
+    let mut depth = 1;
+    loop {
+        match reader.read_event() {
+            Ok(Event::Empty(ref e)) if e.local_name().as_ref() == b"image" => {
+                cover = cover.or(href(e)?);
+            }
+            Ok(Event::Start(ref e)) => {
+                if e.local_name().as_ref() == b"image" {
+                    cover = cover.or(href(e)?);
+                }
+                depth += 1;
+            }
+            Ok(Event::End(_)) => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            Ok(Event::Eof) => return Err(Fb2Error::UnexpectedEof),
+            Err(e) => return Err(e.into()),
+            _ => {}
+        }
+    }
+    Ok(cover)
+}
+
+/// The `href` attribute, whatever prefix the xlink namespace was given
+pub(super) fn href(tag: &BytesStart) -> Result<Option<String>, Fb2Error> {
+    for attr in tag.attributes() {
+        let attr = attr?;
+        if attr.key.local_name().as_ref() == b"href" {
+            return Ok(Some(attr.unescape_value()?.into_owned()));
+        }
+    }
+    Ok(None)
+}
+
+/// Concatenate all text inside the current element, including that of nested elements
+pub(super) fn read_text(reader: &mut Reader<&[u8]>) -> Result<String, Fb2Error> {
+    let mut depth = 1;
+    let mut text = String::new();
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(_)) => depth += 1,
+            Ok(Event::Text(t)) => {
+                text.push_str(&t.unescape()?);
+                text.push(' ');
+            }
+            Ok(Event::End(_)) => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            Ok(Event::Eof) => return Err(Fb2Error::UnexpectedEof),
+            Err(e) => return Err(e.into()),
+            _ => {}
+        }
+    }
+    Ok(text.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use quick_xml::{events::Event, Reader};
+
+    use super::Metadata;
+
+    #[test]
+    fn title_info() {
+        let xml = r##"<title-info>
+    <genre>sf_history</genre>
+    <genre>prose_classic</genre>
+    <author><first-name>Лев</first-name><middle-name>Николаевич</middle-name><last-name>Толстой</last-name></author>
+    <author><nickname>anon</nickname></author>
+    <book-title>Война и мир</book-title>
+    <annotation><p>Роман-эпопея</p><p>в четырёх томах</p></annotation>
+    <date value="1869-01-01">1869</date>
+    <coverpage><image l:href="#cover.jpg"/></coverpage>
+    <lang>ru</lang>
+    <sequence name="Война и мир" number="1"><sequence name="Собрание сочинений"/></sequence>
+  </title-info>"##;
+        let mut reader = Reader::from_str(xml);
+        assert!(matches!(reader.read_event(), Ok(Event::Start(_))));
+        let metadata = Metadata::extract(&mut reader).unwrap();
+        assert_eq!(metadata.title(), Some("Война и мир"));
+        assert_eq!(metadata.authors(), ["Лев Николаевич Толстой", "anon"]);
+        assert_eq!(metadata.genres(), ["sf_history", "prose_classic"]);
+        assert_eq!(metadata.language(), Some("ru"));
+        assert_eq!(metadata.annotation(), Some("Роман-эпопея в четырёх томах"));
+        assert_eq!(metadata.date(), Some("1869"));
+        assert_eq!(metadata.series(), Some(("Война и мир", Some(1.0))));
+        assert_eq!(metadata.cover(), Some("#cover.jpg"));
+    }
+}
@@ -2,6 +2,8 @@ use std::rc::Rc;
 
 use app::App;
 use config::Config;
+use document::TocEntry;
+use epub::{Book, Severity};
 use winit::event_loop::{ControlFlow, EventLoop};
 
 use winit::window::Window;
@@ -9,9 +11,12 @@ use winit::window::Window;
 mod app;
 mod book_handler;
 mod config;
+mod document;
 mod draw;
 mod epub;
+mod fb2;
 mod page;
+mod plain;
 mod text;
 
 pub type SoftBufferType<'a> = softbuffer::Buffer<'a, Rc<Window>, Rc<Window>>;
@@ -19,6 +24,16 @@ pub type SoftBufferType<'a> = softbuffer::Buffer<'a, Rc<Window>, Rc<Window>>;
 fn main() {
     let subscriber = tracing_subscriber::FmtSubscriber::new();
     tracing::subscriber::set_global_default(subscriber).unwrap();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let [command, path] = args.as_slice() {
+        match command.as_str() {
+            "check" => std::process::exit(check(path)),
+            "info" => std::process::exit(info(path)),
+            _ => (),
+        }
+    }
+
     let event_loop = EventLoop::new().unwrap();
     event_loop.set_control_flow(ControlFlow::Wait);
 
@@ -31,3 +46,64 @@ fn main() {
         tracing::error!("app: {:?}", e);
     }
 }
+
+/// Print the problems found in a book, exiting with a failure if any of them are errors
+fn check(path: &str) -> i32 {
+    let limits = Config::load_config().map(|c| c.limits).unwrap_or_default();
+    let report = Book::diagnose(&path, limits);
+    for diagnostic in &report {
+        println!("{}", diagnostic);
+    }
+    let errors = report
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .count();
+    println!(
+        "{}: {} errors, {} warnings",
+        path,
+        errors,
+        report.len() - errors
+    );
+    if errors > 0 {
+        1
+    } else {
+        0
+    }
+}
+
+/// Print what a book says about itself: its title, authors and the rest of its metadata,
+/// its landmarks and its table of contents
+fn info(path: &str) -> i32 {
+    let (limits, rendition) = Config::load_config()
+        .map(|c| (c.limits, c.rendition))
+        .unwrap_or_default();
+    let book = match document::open(&path, limits, &rendition) {
+        Ok(book) => book,
+        Err(e) => {
+            println!("{}: {}", path, e);
+            return 1;
+        }
+    };
+    if let Some(title) = book.title() {
+        println!("title: {}", title);
+    }
+    for author in book.authors() {
+        println!("author: {}", author);
+    }
+    print!("{}", book.details());
+    for (kind, label) in book.landmarks() {
+        println!("landmark: {} ({})", label, kind);
+    }
+    if !book.toc().is_empty() {
+        println!("contents:");
+        print_toc(book.toc().entries(), 1);
+    }
+    0
+}
+
+fn print_toc(entries: &[TocEntry], depth: usize) {
+    for entry in entries {
+        println!("{}{}", "  ".repeat(depth), entry.label());
+        print_toc(entry.children(), depth + 1);
+    }
+}
@@ -1,9 +1,10 @@
 use crate::app::Error;
+use crate::document::Document;
 use crate::draw::{Canvas, Image};
-use crate::epub::{Book, ElementVariant, Node};
+use crate::epub::{ElementVariant, Node};
 use crate::text::caret::Caret;
 use crate::text::fonts::{Family, FontStyle};
-use crate::text::geom::Point;
+use crate::text::geom::{Bounds, Point, Rect};
 use crate::text::typeset::TypesetText;
 use crate::text::{typeset, TextError, TypesetConfig};
 
@@ -32,30 +33,163 @@ enum PageElement {
     Text(TypesetText),
     Hr { start: Point, end: Point },
     Image(Point, crate::draw::Image),
+    Panel(Bounds),
+}
+
+/// A clickable area of the page and the href it points to
+#[derive(Debug)]
+pub struct Link {
+    bounds: Bounds,
+    href: String,
+    noteref: bool,
+}
+impl Link {
+    pub fn href(&self) -> &str {
+        &self.href
+    }
+    /// Whether the link is marked as pointing at a footnote or endnote
+    pub fn is_noteref(&self) -> bool {
+        self.noteref
+    }
 }
 
 #[derive(Debug, Default)]
 pub struct Page {
     text_elements: Vec<PageElement>,
+    anchors: Vec<String>,
+    /// labels of the print pages whose page break markers are on this page
+    print_pages: Vec<String>,
+    links: Vec<Link>,
 }
 
 impl Page {
+    /// A page showing nothing but the given image, scaled to fill the screen
+    pub fn cover(image: &Image, config: &TypesetConfig) -> Self {
+        let bounds = Rect {
+            width: config.page_width,
+            height: config.page_height,
+        };
+        let scaled = image.rescale(image.fit_scale(bounds));
+        let x = (config.page_width - scaled.size.width) / 2;
+        let y = (config.page_height - scaled.size.height) / 2;
+        let point = Point::new(x as f32, y as f32);
+        Self {
+            text_elements: vec![PageElement::Image(point, scaled)],
+            ..Default::default()
+        }
+    }
+
+    /// Whether the element with the given id starts on this page
+    pub fn has_anchor(&self, id: &str) -> bool {
+        self.anchors.iter().any(|a| a == id)
+    }
+
+    /// The print pages that begin on this page, in order
+    pub fn print_pages(&self) -> &[String] {
+        &self.print_pages
+    }
+
+    /// Move the markers met since the last content was placed onto this page
+    fn place_markers(&mut self, anchors: &mut Vec<String>, print_pages: &mut Vec<String>) {
+        self.anchors.append(anchors);
+        self.print_pages.append(print_pages);
+    }
+
+    /// The link under the given point, if any
+    pub fn link_at(&self, point: Point) -> Option<&Link> {
+        self.links.iter().find(|l| l.bounds.contains(point))
+    }
+
+    fn add_link(
+        &mut self,
+        text: &TypesetText,
+        link: (&str, bool),
+        config: &TypesetConfig,
+        height: f32,
+    ) {
+        let Ok(face) = config.family.face(text.style) else {
+            return;
+        };
+        let scale_factor = face.scale_factor(text.point_size);
+        let (href, noteref) = link;
+        let links = line_bounds(text, scale_factor, height).map(|bounds| Link {
+            bounds,
+            href: href.to_owned(),
+            noteref,
+        });
+        self.links.extend(links);
+    }
+
+    /// Move the content to the bottom of the screen, on a panel covering whatever is
+    /// drawn underneath
+    pub fn into_panel(mut self, config: &TypesetConfig) -> Result<Self, Error> {
+        let line_height = config
+            .family
+            .face(FontStyle::default())?
+            .scaled_height(config.point_size)?;
+        let bottom = self
+            .text_elements
+            .iter()
+            .map(|e| match e {
+                PageElement::Text(t) => t.glyphs.last().map_or(0.0, |g| g.pos.y + line_height),
+                PageElement::Hr { end, .. } => end.y,
+                PageElement::Image(point, image) => point.y + image.size.height as f32,
+                PageElement::Panel(bounds) => bounds.max.y,
+            })
+            .fold(0.0, f32::max);
+        let dy = (config.page_height as f32 - config.vertical_margin as f32 - bottom).max(0.0);
+
+        for e in self.text_elements.iter_mut() {
+            match e {
+                PageElement::Text(t) => t.glyphs.iter_mut().for_each(|g| g.pos.y += dy),
+                PageElement::Hr { start, end } => {
+                    *start = start.add_y(dy);
+                    *end = end.add_y(dy);
+                }
+                PageElement::Image(point, _) => *point = point.add_y(dy),
+                PageElement::Panel(bounds) => {
+                    bounds.min = bounds.min.add_y(dy);
+                    bounds.max = bounds.max.add_y(dy);
+                }
+            }
+        }
+        for l in self.links.iter_mut() {
+            l.bounds.min = l.bounds.min.add_y(dy);
+            l.bounds.max = l.bounds.max.add_y(dy);
+        }
+
+        let panel = Bounds {
+            min: Point::new(0.0, dy),
+            max: Point::new(config.page_width as f32, config.page_height as f32),
+        };
+        self.text_elements.insert(0, PageElement::Panel(panel));
+        Ok(self)
+    }
+
     pub fn raster(&self, fam: &Family, canvas: &mut Canvas) -> Result<(), Error> {
         for e in &self.text_elements {
             match e {
                 PageElement::Text(t) => canvas.text(fam, t)?,
                 PageElement::Hr { start, end } => canvas.draw_line(start, end)?,
                 PageElement::Image(point, image) => canvas.image(point, image)?,
+                PageElement::Panel(bounds) => {
+                    canvas.clear(bounds)?;
+                    let border = bounds.min.add_y(2.0).add_x(bounds.max.x - bounds.min.x);
+                    canvas.draw_line(&bounds.min, &border)?;
+                }
             }
         }
         Ok(())
     }
 }
 
+/// Lay out a document, or a part of it, as pages. `path` is where the document is in the
+/// book, for resolving the images it refers to
 pub fn paginate(
     content: &Node,
+    path: &str,
     config: &TypesetConfig,
-    book: &mut Book,
+    book: &mut dyn Document,
 ) -> Result<Vec<Page>, Error> {
     let mut pages = vec![];
     let mut p = Page::default();
@@ -63,8 +197,29 @@ pub fn paginate(
     let mut text_type = FontStyle::Regular;
     let mut caret = Caret::new(config)?;
     let mut break_type = None;
+    // ids of elements whose content hasn't been placed on a page yet
+    let mut anchors = vec![];
+    // labels of print page breaks met since then
+    let mut print_pages = vec![];
+    // the enclosing link and how many of its text nodes are still to come
+    let mut link: Option<((&str, bool), usize)> = None;
 
-    for node in content.iter() {
+    let mut nodes = content.iter();
+    while let Some(node) = nodes.next() {
+        // notes are shown on demand rather than in the flow, unless they're what we're showing
+        if node.element().is_some_and(|e| e.is_note()) && !std::ptr::eq(node, content) {
+            nodes.skip_children();
+            continue;
+        }
+        if let Some(id) = node.element().and_then(|e| e.id()) {
+            anchors.push(id.to_owned());
+        }
+        if let Some(label) = node.element().and_then(|e| e.page_break()) {
+            print_pages.push(label);
+            // the marker's text is the page number rather than part of the flow
+            nodes.skip_children();
+            continue;
+        }
         match node {
             Node::Element(elem) => match elem.variant() {
                 ElementVariant::H1 | ElementVariant::H2 | ElementVariant::H3 => {
@@ -84,22 +239,31 @@ pub fn paginate(
                 ElementVariant::I => {
                     text_type = FontStyle::Italic;
                 }
+                ElementVariant::A => {
+                    if let Some(href) = elem.attribute("href") {
+                        let texts = node.iter().filter(|n| matches!(n, Node::Text(_))).count();
+                        link = Some(((href.value(), elem.is_noteref()), texts));
+                    }
+                }
                 ElementVariant::Image => {
-                    let attr = elem
-                        .attribute("xlink:href")
-                        .ok_or_else(|| Error::ImageTag)?;
-                    let content = book.file(attr.value())?;
-                    let image = Image::open(content)?;
-
-                    let img_height = config.page_height - 2 * config.vertical_margin as usize;
-                    let scale = img_height as f32 / image.size.height as f32;
-                    let small_image = image.rescale(scale);
-                    let hoffset = (config.page_width
-                        - (2 * config.horizontal_margin) as usize
-                        - small_image.size.width)
-                        / 2;
+                    let href = elem.image_href().ok_or(Error::ImageTag)?;
+                    let image = match book.image(path, href) {
+                        Ok(image) => image,
+                        Err(e) => {
+                            tracing::warn!("unable to load image {}: {}", href, e);
+                            continue;
+                        }
+                    };
+
+                    let bounds = Rect {
+                        width: config.page_width - 2 * config.horizontal_margin as usize,
+                        height: config.page_height - 2 * config.vertical_margin as usize,
+                    };
+                    let small_image = image.rescale(image.fit_scale(bounds));
+                    let hoffset = (bounds.width - small_image.size.width) / 2;
 
                     let point = caret.point().add_x(hoffset as f32);
+                    p.place_markers(&mut anchors, &mut print_pages);
                     p.text_elements.push(PageElement::Image(point, small_image));
                 }
                 ElementVariant::Br => {
@@ -112,6 +276,7 @@ pub fn paginate(
                     let midline = caret.scaled_height() / 2.0;
                     let start = Point::new(s.x, (s.y + midline).floor());
                     let end = Point::new(e as f32, (s.y + midline).ceil());
+                    p.place_markers(&mut anchors, &mut print_pages);
                     p.text_elements.push(PageElement::Hr { start, end });
                 }
                 _ => {
@@ -119,8 +284,9 @@ pub fn paginate(
                 }
             },
             Node::Text(text) => {
-                if !p.text_elements.is_empty() {
-                    if let Some(bt) = break_type.take() {
+                // breaks at the top of a page are dropped rather than carried to the next text
+                if let Some(bt) = break_type.take() {
+                    if !p.text_elements.is_empty() {
                         let lines = match bt {
                             BreakType::Block => 1.3,
                             BreakType::Line => 1.0,
@@ -135,23 +301,40 @@ pub fn paginate(
                     }
                 }
 
-                let mut remaining = Some(text.chars().skip(0));
-                while let Some(next) = remaining.take() {
-                    let res = typeset::typeset(config, &mut caret, next, text_type);
+                p.place_markers(&mut anchors, &mut print_pages);
+
+                let text_link = match link.as_mut() {
+                    Some((l, remaining)) if *remaining > 0 => {
+                        *remaining -= 1;
+                        Some(*l)
+                    }
+                    _ => None,
+                };
+
+                let mut offset = 0;
+                loop {
+                    let remaining = text.chars().skip(offset);
+                    let res = typeset::typeset(config, &mut caret, remaining, text_type);
                     match res {
                         Ok(typeset_text) => {
+                            if let Some(l) = text_link {
+                                p.add_link(&typeset_text, l, config, caret.scaled_height());
+                            }
                             p.text_elements.push(PageElement::Text(typeset_text));
+                            break;
                         }
                         Err(TextError::ContentOverflow(processed, index)) => {
                             // commit the pre-overflow part
+                            if let Some(l) = text_link {
+                                p.add_link(&processed, l, config, caret.scaled_height());
+                            }
                             p.text_elements.push(PageElement::Text(processed));
                             pages.push(p);
 
                             // start a new page
                             p = Page::default();
                             caret.reset_location();
-                            let overflow = text.chars().skip(index);
-                            remaining = Some(overflow);
+                            offset += index;
                         }
                         Err(err) => return Err(err.into()),
                     }
@@ -163,6 +346,63 @@ pub fn paginate(
     }
 
     // add the last non-overflowed page
+    p.place_markers(&mut anchors, &mut print_pages);
     pages.push(p);
     Ok(pages)
 }
+
+/// The area covered by each line of typeset text
+fn line_bounds(
+    text: &TypesetText,
+    scale_factor: f32,
+    height: f32,
+) -> impl Iterator<Item = Bounds> + use<'_> {
+    text.glyphs
+        .chunk_by(|a, b| a.pos.y == b.pos.y)
+        .map(move |line| {
+            let first = &line[0];
+            let last = &line[line.len() - 1];
+            Bounds {
+                min: first.pos,
+                max: Point::new(
+                    last.pos.x + last.advance * scale_factor,
+                    first.pos.y + height,
+                ),
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::epub::{Book, Limits, Node};
+    use crate::text::{fonts::FontIndexer, TypesetConfig};
+
+    use super::{paginate, PageElement};
+
+    #[test]
+    fn no_break_at_top_of_page() {
+        let family = FontIndexer::new("testfiles/fonts")
+            .get_family("Vollkorn")
+            .unwrap();
+        let config = TypesetConfig {
+            family,
+            ..Default::default()
+        };
+        let mut book = Book::new(&"testfiles/epubs/frankenstein.epub", Limits::default()).unwrap();
+        // the paragraph's break falls at the top of the page, so it mustn't end up between
+        // the paragraph's first words and the emphasis after them
+        let xml = b"<html><body><p>Hello <i>world</i></p></body></html>";
+        let content = Node::new(xml, &Limits::default()).unwrap();
+        let pages = paginate(&content, "", &config, &mut book).unwrap();
+        let lines: Vec<_> = pages[0]
+            .text_elements
+            .iter()
+            .filter_map(|e| match e {
+                PageElement::Text(t) => Some(t.glyphs.first()?.pos.y),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], lines[1]);
+    }
+}
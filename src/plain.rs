@@ -0,0 +1,8 @@
+mod book;
+mod encoding;
+mod error;
+mod markdown;
+mod text;
+
+pub use book::Book;
+pub use error::PlainError;
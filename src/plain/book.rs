@@ -0,0 +1,296 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::Read,
+    iter::Peekable,
+    path::{Path, PathBuf},
+};
+
+use crate::document::{Document, DocumentError, Location, Section, Toc, TocEntry};
+use crate::draw::Image;
+use crate::epub::{resolve_path, Element, ElementVariant, Limits, Node};
+
+use super::{encoding::decode, markdown, text, PlainError};
+
+/// A plain text or Markdown document, split into sections at its chapter headings
+#[derive(Debug, Default)]
+pub struct Book {
+    title: Option<String>,
+    authors: Vec<String>,
+    sections: Vec<Section>,
+    /// the section each element id is in
+    ids: HashMap<String, usize>,
+    toc: Toc,
+    /// the directory the document is in, which images are looked for in
+    dir: PathBuf,
+    limits: Limits,
+}
+
+impl Book {
+    /// Open a text file, reading it as Markdown if it has an `.md` or `.markdown` extension
+    pub fn new<P: AsRef<Path>>(path: &P, limits: Limits) -> Result<Self, PlainError> {
+        let path = path.as_ref();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let file = File::open(path)?;
+        if file.metadata()?.len() > limits.max_entry_size {
+            return Err(PlainError::TooLarge(
+                name.into_owned(),
+                limits.max_entry_size,
+            ));
+        }
+        let mut bytes = vec![];
+        file.take(limits.max_entry_size).read_to_end(&mut bytes)?;
+        let text = decode(&bytes);
+
+        let markdown = path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("md") || e.eq_ignore_ascii_case("markdown"));
+        let mut book = if markdown {
+            Self::from_markdown(&text, &name, limits)?
+        } else {
+            Self::from_text(&text, &name, limits)?
+        };
+        book.dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        Ok(book)
+    }
+
+    /// Read plain text. Its title and author come from a header like Project Gutenberg's,
+    /// or failing that the title is the file name
+    pub fn from_text(text: &str, path: &str, limits: Limits) -> Result<Self, PlainError> {
+        let (title, authors) = text::header(text);
+        let mut book = Self::build(text::parse(text), path, limits)?;
+        book.title = title.or(book.title);
+        book.authors = authors;
+        Ok(book)
+    }
+
+    /// Read Markdown. Its title is the first level 1 heading, or failing that the file name
+    pub fn from_markdown(text: &str, path: &str, limits: Limits) -> Result<Self, PlainError> {
+        let mut book = Self::build(markdown::parse(text, limits.max_depth)?, path, limits)?;
+        let title = book
+            .sections
+            .iter()
+            .flat_map(|s| s.node.iter())
+            .find(|n| {
+                n.element()
+                    .is_some_and(|e| e.variant() == ElementVariant::H1)
+            })
+            .map(label);
+        book.title = title.or(book.title);
+        Ok(book)
+    }
+
+    /// A book of the given sections, whose toc lists their headings. `path` is the name of
+    /// the file they were read from
+    fn build(sections: Vec<Node>, path: &str, limits: Limits) -> Result<Self, PlainError> {
+        if sections.is_empty() {
+            return Err(PlainError::Empty);
+        }
+        let mut book = Self {
+            title: Path::new(path)
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned()),
+            limits,
+            ..Default::default()
+        };
+        let mut headings = vec![];
+        for (index, node) in sections.into_iter().enumerate() {
+            let location = Location::new(format!("section-{}", index), path.to_owned(), true);
+            for n in node.iter() {
+                let Some(el) = n.element() else {
+                    continue;
+                };
+                if let Some(id) = el.id() {
+                    book.ids.insert(id.to_owned(), index);
+                }
+                let level = match el.name() {
+                    "h1" => 1,
+                    "h2" => 2,
+                    "h3" => 3,
+                    _ => continue,
+                };
+                let location = location.with_fragment(el.id().map(str::to_owned));
+                headings.push((level, label(n), location));
+            }
+            book.sections.push(Section { location, node });
+        }
+        book.toc = Toc::new(nest(&mut headings.into_iter().peekable(), 0));
+        Ok(book)
+    }
+
+    fn position_of(&self, id: &str) -> Result<usize, DocumentError> {
+        self.sections
+            .iter()
+            .position(|s| s.location.id() == id)
+            .ok_or(DocumentError::SectionNotFound(id.to_owned()))
+    }
+
+    /// A copy of the section at the given position, for laying out
+    fn section_at(&self, position: usize, fragment: Option<String>) -> Section {
+        let section = &self.sections[position];
+        Section {
+            location: section.location.with_fragment(fragment),
+            node: section.node.clone(),
+        }
+    }
+
+    /// Load an image from next to the document. Images can't be looked for above the
+    /// document's directory, nor on the web
+    fn load_image(&self, from: &str, href: &str) -> Result<Image, PlainError> {
+        let path = resolve_path(from, href).ok_or(PlainError::ImageOutside(href.to_owned()))?;
+        let file = File::open(self.dir.join(&path))?;
+        if file.metadata()?.len() > self.limits.max_entry_size {
+            return Err(PlainError::TooLarge(path, self.limits.max_entry_size));
+        }
+        let mut bytes = vec![];
+        file.take(self.limits.max_entry_size)
+            .read_to_end(&mut bytes)?;
+        Ok(Image::from_bytes(&bytes)?)
+    }
+}
+
+/// The section element holding the blocks of the section at the given position
+pub(super) fn section(index: usize, children: Vec<Node>) -> Node {
+    let attributes = vec![("id".to_owned(), format!("section-{}", index))];
+    let section = Element::build("section", ElementVariant::Section, attributes, children);
+    Node::Element(section)
+}
+
+pub(super) fn is_heading(node: &Node) -> bool {
+    node.element().is_some_and(|e| {
+        matches!(
+            e.variant(),
+            ElementVariant::H1 | ElementVariant::H2 | ElementVariant::H3
+        )
+    })
+}
+
+/// All the text in a node, as one line
+fn label(node: &Node) -> String {
+    let text: Vec<_> = node.iter().filter_map(Node::text).collect();
+    text.concat()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Nest the headings after one of the given level under it, up to the next heading of the
+/// same level or above. Levels that are skipped don't matter, so a book of chapters without
+/// parts has them all at the top
+fn nest<I>(headings: &mut Peekable<I>, parent: u8) -> Vec<TocEntry>
+where
+    I: Iterator<Item = (u8, String, Location)>,
+{
+    let mut entries = vec![];
+    while let Some((level, label, location)) = headings.next_if(|(level, ..)| *level > parent) {
+        let children = nest(headings, level);
+        entries.push(TocEntry::new(label, Some(location), children));
+    }
+    entries
+}
+
+impl Document for Book {
+    fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    fn authors(&self) -> Vec<&str> {
+        self.authors.iter().map(String::as_str).collect()
+    }
+
+    fn first(&mut self) -> Result<Section, DocumentError> {
+        Ok(self.section_at(0, None))
+    }
+
+    fn next(&mut self, id: &str) -> Result<Section, DocumentError> {
+        let position = self.position_of(id)? + 1;
+        if position == self.sections.len() {
+            return Err(DocumentError::SectionNotFound(id.to_owned()));
+        }
+        Ok(self.section_at(position, None))
+    }
+
+    fn prev(&mut self, id: &str) -> Result<Section, DocumentError> {
+        let position = self
+            .position_of(id)?
+            .checked_sub(1)
+            .ok_or(DocumentError::SectionNotFound(id.to_owned()))?;
+        Ok(self.section_at(position, None))
+    }
+
+    fn section(&mut self, location: &Location) -> Result<Section, DocumentError> {
+        let position = self.position_of(location.id())?;
+        Ok(self.section_at(position, location.fragment().map(str::to_owned)))
+    }
+
+    fn position(&self, id: &str) -> Option<usize> {
+        self.position_of(id).ok()
+    }
+
+    /// Only links to headings, as in `#chapter-one`, point inside the document
+    fn resolve_link(&self, _from: &Location, href: &str) -> Option<Location> {
+        let id = href.strip_prefix('#')?;
+        let position = *self.ids.get(id)?;
+        let location = &self.sections[position].location;
+        Some(location.with_fragment(Some(id.to_owned())))
+    }
+
+    fn image(&mut self, from: &str, href: &str) -> Result<Image, DocumentError> {
+        Ok(self.load_image(from, href)?)
+    }
+
+    fn toc(&self) -> &Toc {
+        &self.toc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::document::Document;
+    use crate::epub::Limits;
+
+    use super::Book;
+
+    #[test]
+    fn text_toc() {
+        let text = "PREFACE\n\nA word first.\n\nPART I\n\nCHAPTER 1\n\nOne.\n\n\
+CHAPTER 2\n\nTwo.\n\nPART II\n\nCHAPTER 3\n\nThree.\n";
+        let mut book = Book::from_text(text, "tale.txt", Limits::default()).unwrap();
+        assert_eq!(book.title(), Some("tale"));
+        assert!(book.authors().is_empty());
+
+        let labels: Vec<_> = book.toc().entries().iter().map(|e| e.label()).collect();
+        assert_eq!(labels, ["PREFACE", "PART I", "PART II"]);
+        let chapters: Vec<_> = book.toc().entries()[1]
+            .children()
+            .iter()
+            .map(|e| e.label())
+            .collect();
+        assert_eq!(chapters, ["CHAPTER 1", "CHAPTER 2"]);
+
+        let first = book.first().unwrap();
+        let second = book.next(first.location.id()).unwrap();
+        let location = book.toc().entries()[1].location().unwrap().clone();
+        assert_eq!(location.id(), second.location.id());
+        assert!(second
+            .node
+            .find_by_id(location.fragment().unwrap())
+            .is_some());
+        assert!(book.next("section-3").is_err());
+        assert!(book.prev("section-0").is_err());
+    }
+
+    #[test]
+    fn markdown_links() {
+        let markdown = "# Notes\n\nSee [usage](#usage-notes).\n\n## Usage notes\n\nText.\n";
+        let mut book = Book::from_markdown(markdown, "notes.md", Limits::default()).unwrap();
+        assert_eq!(book.title(), Some("Notes"));
+        let first = book.first().unwrap();
+        let target = book.resolve_link(&first.location, "#usage-notes").unwrap();
+        assert_eq!(target.id(), "section-1");
+        assert!(book.resolve_link(&first.location, "other.md").is_none());
+        assert!(book.image("notes.md", "https://example.com/a.png").is_err());
+
+        assert!(Book::from_markdown("", "empty.md", Limits::default()).is_err());
+    }
+}
@@ -0,0 +1,53 @@
+use std::borrow::Cow;
+
+use chardetng::EncodingDetector;
+use encoding_rs::Encoding;
+
+/// Decode text that doesn't say what encoding it's in. A byte order mark settles it, valid
+/// UTF-8 is taken as it is, and anything else is guessed from which byte sequences it uses
+pub fn decode(bytes: &[u8]) -> Cow<'_, str> {
+    if let Some((encoding, bom_len)) = Encoding::for_bom(bytes) {
+        let (text, _) = encoding.decode_without_bom_handling(&bytes[bom_len..]);
+        return text;
+    }
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return Cow::Borrowed(text);
+    }
+    let mut detector = EncodingDetector::new();
+    detector.feed(bytes, true);
+    let encoding = detector.guess(None, true);
+    tracing::info!("guessed the text is {}", encoding.name());
+    let (text, malformed) = encoding.decode_without_bom_handling(bytes);
+    if malformed {
+        tracing::warn!("replaced malformed {} sequences", encoding.name());
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use encoding_rs::{WINDOWS_1251, WINDOWS_1252};
+
+    use super::decode;
+
+    #[test]
+    fn utf() {
+        assert_eq!(decode("café".as_bytes()), "café");
+        let with_bom = [&[0xef, 0xbb, 0xbf][..], "café".as_bytes()].concat();
+        assert_eq!(decode(&with_bom), "café");
+
+        let le: Vec<u8> = "café".encode_utf16().flat_map(u16::to_le_bytes).collect();
+        assert_eq!(decode(&[&[0xff, 0xfe][..], &le].concat()), "café");
+    }
+
+    #[test]
+    fn guessed() {
+        let text = "Он вошёл в комнату и сел у окна. Было тихо, только часы тикали на стене.";
+        let (bytes, _, _) = WINDOWS_1251.encode(text);
+        assert_eq!(decode(&bytes), text);
+
+        let text = "The café was naïve about “quotes” — and it showed.";
+        let (bytes, _, _) = WINDOWS_1252.encode(text);
+        assert_eq!(decode(&bytes), text);
+    }
+}
@@ -0,0 +1,22 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum PlainError {
+    #[error("file io")]
+    FileIO(#[from] std::io::Error),
+
+    #[error("{0} is larger than {1} bytes")]
+    TooLarge(String, u64),
+
+    #[error("markup nested deeper than {0} levels")]
+    TooDeep(usize),
+
+    #[error("document has no text")]
+    Empty,
+
+    #[error("image outside the document's directory: {0}")]
+    ImageOutside(String),
+
+    #[error("image")]
+    Image(#[from] crate::draw::Error),
+}
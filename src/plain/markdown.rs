@@ -0,0 +1,363 @@
+use std::collections::HashSet;
+
+use pulldown_cmark::{Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+
+use crate::epub::{Element, ElementVariant, Node};
+
+use super::{
+    book::{is_heading, section},
+    PlainError,
+};
+
+/// An element whose children are still being read
+struct Open {
+    name: String,
+    variant: ElementVariant,
+    attributes: Vec<(String, String)>,
+    children: Vec<Node>,
+}
+
+/// Turns the parser's events into sections, each starting at a top level heading
+#[derive(Default)]
+struct Builder {
+    sections: Vec<Node>,
+    /// the finished blocks of the section being read
+    current: Vec<Node>,
+    stack: Vec<Open>,
+    /// the number of the next item of each open list, or nothing for bullet lists
+    lists: Vec<Option<u64>>,
+    /// the bullet or number of the list item whose text hasn't started yet
+    marker: Option<String>,
+    in_code: bool,
+    ids: HashSet<String>,
+}
+impl Builder {
+    fn open(&mut self, tag: Tag, max_depth: usize) -> Result<(), PlainError> {
+        if self.stack.len() >= max_depth {
+            return Err(PlainError::TooDeep(max_depth));
+        }
+        let mut attributes = vec![];
+        let (name, variant) = match tag {
+            Tag::Paragraph => ("p".to_owned(), ElementVariant::P),
+            Tag::Heading { level, id, .. } => {
+                // level 1 and 2 headings start sections, unless all that's before them in
+                // the section is other headings
+                if self.stack.is_empty()
+                    && level <= HeadingLevel::H2
+                    && self.current.iter().any(|n| !is_heading(n))
+                {
+                    let children = std::mem::take(&mut self.current);
+                    self.sections.push(section(self.sections.len(), children));
+                }
+                if let Some(id) = id {
+                    self.ids.insert(id.to_string());
+                    attributes.push(("id".to_owned(), id.to_string()));
+                }
+                let variant = match level {
+                    HeadingLevel::H1 => ElementVariant::H1,
+                    HeadingLevel::H2 => ElementVariant::H2,
+                    _ => ElementVariant::H3,
+                };
+                (level.to_string(), variant)
+            }
+            Tag::BlockQuote(_) => ("blockquote".to_owned(), ElementVariant::Blockquote),
+            Tag::CodeBlock(_) => {
+                self.in_code = true;
+                ("pre".to_owned(), ElementVariant::Div)
+            }
+            Tag::List(start) => {
+                self.lists.push(start);
+                let name = if start.is_some() { "ol" } else { "ul" };
+                (name.to_owned(), ElementVariant::Div)
+            }
+            Tag::Item => {
+                self.marker = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        Some(format!("{}. ", *number - 1))
+                    }
+                    _ => Some("• ".to_owned()),
+                };
+                ("li".to_owned(), ElementVariant::P)
+            }
+            Tag::Emphasis => ("em".to_owned(), ElementVariant::I),
+            Tag::Strong => ("strong".to_owned(), ElementVariant::B),
+            Tag::Link { dest_url, .. } => {
+                attributes.push(("href".to_owned(), dest_url.to_string()));
+                ("a".to_owned(), ElementVariant::A)
+            }
+            Tag::Image { dest_url, .. } => {
+                attributes.push(("src".to_owned(), dest_url.to_string()));
+                ("img".to_owned(), ElementVariant::Image)
+            }
+            _ => ("div".to_owned(), ElementVariant::Ignored),
+        };
+        self.stack.push(Open {
+            name,
+            variant,
+            attributes,
+            children: vec![],
+        });
+        Ok(())
+    }
+
+    fn close(&mut self, tag: TagEnd) {
+        let Some(mut open) = self.stack.pop() else {
+            return;
+        };
+        match tag {
+            TagEnd::CodeBlock => self.in_code = false,
+            TagEnd::List(_) => {
+                self.lists.pop();
+            }
+            TagEnd::Item => self.marker = None,
+            // the alt text is shown instead of the image only if it fails to load
+            TagEnd::Image => {
+                let alt = open.children.iter().flat_map(text).collect();
+                open.attributes.push(("alt".to_owned(), alt));
+                open.children.clear();
+            }
+            TagEnd::Heading(_) if !open.attributes.iter().any(|(k, _)| k == "id") => {
+                let label: String = open.children.iter().flat_map(text).collect();
+                let id = self.unique_id(&slug(&label));
+                open.attributes.push(("id".to_owned(), id));
+            }
+            _ => (),
+        }
+        let element = Element::build(&open.name, open.variant, open.attributes, open.children);
+        self.push(Node::Element(element));
+    }
+
+    fn push(&mut self, node: Node) {
+        match self.stack.last_mut() {
+            Some(parent) => parent.children.push(node),
+            None => self.current.push(node),
+        }
+    }
+
+    /// Add text to the innermost open element, joining it to the text before it. Runs of
+    /// whitespace collapse to a single space, but aren't dropped, since they separate the
+    /// text from the emphasis around it
+    fn text(&mut self, raw: &str) {
+        let mut text = String::with_capacity(raw.len());
+        for c in raw.chars() {
+            if !c.is_whitespace() {
+                text.push(c);
+            } else if !text.ends_with(' ') {
+                text.push(' ');
+            }
+        }
+        if let Some(marker) = self.marker.take() {
+            text.insert_str(0, &marker);
+        }
+        let children = self.children();
+        match children.last_mut() {
+            Some(Node::Text(last)) => {
+                let text = if last.ends_with(' ') {
+                    text.trim_start()
+                } else {
+                    &text
+                };
+                last.push_str(text);
+            }
+            last => {
+                // blocks don't start with a space
+                let text = if last.is_none() {
+                    text.trim_start()
+                } else {
+                    &text
+                };
+                if !text.is_empty() {
+                    children.push(Node::Text(text.to_owned()));
+                }
+            }
+        }
+    }
+
+    /// Code keeps its lines
+    fn code(&mut self, text: &str) {
+        for line in text.split_inclusive('\n') {
+            self.push(Node::Text(line.trim_end_matches('\n').to_owned()));
+            if line.ends_with('\n') {
+                self.push(br());
+            }
+        }
+    }
+
+    fn children(&mut self) -> &mut Vec<Node> {
+        match self.stack.last_mut() {
+            Some(parent) => &mut parent.children,
+            None => &mut self.current,
+        }
+    }
+
+    /// The id, numbered if it's already been used
+    fn unique_id(&mut self, id: &str) -> String {
+        let mut unique = id.to_owned();
+        let mut n = 0;
+        while !self.ids.insert(unique.clone()) {
+            n += 1;
+            unique = format!("{}-{}", id, n);
+        }
+        unique
+    }
+}
+
+/// Map Markdown onto the nodes we lay out, split into sections at its level 1 and 2
+/// headings. Headings get the ids GitHub would give them, so links like `#usage` work
+pub fn parse(text: &str, max_depth: usize) -> Result<Vec<Node>, PlainError> {
+    let mut builder = Builder::default();
+    for event in Parser::new_ext(text, Options::ENABLE_HEADING_ATTRIBUTES) {
+        match event {
+            Event::Start(tag) => builder.open(tag, max_depth)?,
+            Event::End(tag) => builder.close(tag),
+            Event::Text(text) if builder.in_code => builder.code(&text),
+            Event::Text(text) | Event::Code(text) => builder.text(&text),
+            Event::SoftBreak => builder.text(" "),
+            Event::HardBreak => builder.push(br()),
+            Event::Rule => {
+                let rule = Element::build("hr", ElementVariant::Hr, vec![], vec![]);
+                builder.push(Node::Element(rule));
+            }
+            _ => (),
+        }
+    }
+    if !builder.current.is_empty() {
+        let children = std::mem::take(&mut builder.current);
+        builder
+            .sections
+            .push(section(builder.sections.len(), children));
+    }
+    Ok(builder.sections)
+}
+
+fn br() -> Node {
+    Node::Element(Element::build("br", ElementVariant::Br, vec![], vec![]))
+}
+
+fn text(node: &Node) -> impl Iterator<Item = &str> {
+    node.iter().filter_map(Node::text)
+}
+
+/// Lowercase, with spaces turned into hyphens and punctuation other than hyphens and
+/// underscores dropped
+fn slug(label: &str) -> String {
+    label
+        .trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            c if c.is_whitespace() => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::epub::{ElementVariant, Node};
+
+    use super::parse;
+
+    const MARKDOWN: &str = "# The Book
+
+An *introduction* with **bold** words
+across two lines.
+
+## Chapter One {#one}
+
+- first
+- second
+
+1. uno
+2. dos
+
+---
+
+![A map](images/map.png)
+
+## Chapter One
+
+> quoted
+> text
+
+```
+let x = 1;
+let y = 2;
+```
+
+### Details
+
+See [the start](#one).
+
+### Details
+";
+
+    fn texts(node: &Node) -> Vec<&str> {
+        node.iter().filter_map(Node::text).collect()
+    }
+
+    fn variants(node: &Node) -> Vec<ElementVariant> {
+        node.iter()
+            .filter_map(|n| Some(n.element()?.variant()))
+            .collect()
+    }
+
+    #[test]
+    fn sections() {
+        let sections = parse(MARKDOWN, 256).unwrap();
+        assert_eq!(sections.len(), 3);
+
+        assert_eq!(
+            texts(&sections[0]),
+            [
+                "The Book",
+                "An ",
+                "introduction",
+                " with ",
+                "bold",
+                " words across two lines."
+            ]
+        );
+        assert!(variants(&sections[0]).contains(&ElementVariant::I));
+        assert!(variants(&sections[0]).contains(&ElementVariant::B));
+        assert!(sections[0].find_by_id("the-book").is_some());
+
+        assert_eq!(
+            texts(&sections[1]),
+            ["Chapter One", "• first", "• second", "1. uno", "2. dos"]
+        );
+        assert!(sections[1].find_by_id("one").is_some());
+        assert!(variants(&sections[1]).contains(&ElementVariant::Hr));
+        let image = sections[1]
+            .iter()
+            .filter_map(Node::element)
+            .find(|e| e.variant() == ElementVariant::Image)
+            .unwrap();
+        assert_eq!(image.image_href(), Some("images/map.png"));
+        assert_eq!(image.attribute("alt").unwrap().value(), "A map");
+
+        assert!(sections[2].find_by_id("chapter-one").is_some());
+        // repeated headings get numbered ids
+        assert!(sections[2].find_by_id("details").is_some());
+        assert!(sections[2].find_by_id("details-1").is_some());
+        assert_eq!(
+            texts(&sections[2])[..4],
+            ["Chapter One", "quoted text", "let x = 1;", "let y = 2;"]
+        );
+        let link = sections[2]
+            .iter()
+            .filter_map(Node::element)
+            .find(|e| e.variant() == ElementVariant::A)
+            .unwrap();
+        assert_eq!(link.attribute("href").unwrap().value(), "#one");
+    }
+
+    #[test]
+    fn too_deep() {
+        let nested = format!("{}deep", "> ".repeat(20));
+        assert!(parse(&nested, 16).is_err());
+        assert!(parse(&nested, 32).is_ok());
+    }
+}
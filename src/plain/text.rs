@@ -0,0 +1,267 @@
+use crate::epub::{Element, ElementVariant, Node};
+
+use super::book::{is_heading, section};
+
+/// Headings that divide the book into parts, each holding chapters
+const PARTS: [&str; 3] = ["part", "book", "volume"];
+/// Headings of the chapters themselves, when followed by a number
+const CHAPTERS: [&str; 2] = ["chapter", "letter"];
+/// Headings that stand on their own, without a number
+const UNNUMBERED: [&str; 6] = [
+    "prologue",
+    "epilogue",
+    "preface",
+    "foreword",
+    "afterword",
+    "introduction",
+];
+/// Numbers as they're spelled out in headings like "Part Two"
+const NUMBER_WORDS: [&str; 30] = [
+    "one",
+    "two",
+    "three",
+    "four",
+    "five",
+    "six",
+    "seven",
+    "eight",
+    "nine",
+    "ten",
+    "eleven",
+    "twelve",
+    "thirteen",
+    "fourteen",
+    "fifteen",
+    "sixteen",
+    "seventeen",
+    "eighteen",
+    "nineteen",
+    "twenty",
+    "first",
+    "second",
+    "third",
+    "fourth",
+    "fifth",
+    "sixth",
+    "seventh",
+    "eighth",
+    "ninth",
+    "tenth",
+];
+
+/// Lines after which the front matter stops being searched for the title and author
+const HEADER_LINES: usize = 100;
+
+/// The heading a line would be, if it's a chapter or part heading such as "CHAPTER XII.",
+/// "Part Two" or "Letter 4: Arrival". Anything after the number has to be set off by
+/// punctuation, so ordinary sentences that start with "Book one" aren't mistaken for one
+pub fn heading(line: &str) -> Option<ElementVariant> {
+    let line = line.trim();
+    if line.chars().count() > 80 {
+        return None;
+    }
+    let lower = line.to_lowercase();
+    let mut words = lower.split_whitespace();
+    let (keyword, separated) = word(words.next()?);
+    if UNNUMBERED.contains(&keyword) {
+        return (separated || words.next().is_none()).then_some(ElementVariant::H2);
+    }
+    let variant = if PARTS.contains(&keyword) {
+        ElementVariant::H1
+    } else if CHAPTERS.contains(&keyword) {
+        ElementVariant::H2
+    } else {
+        return None;
+    };
+    let (number, separated) = word(words.next()?);
+    let is_number = number.chars().all(|c| c.is_ascii_digit())
+        || number.chars().all(|c| "ivxlcdm".contains(c))
+        || NUMBER_WORDS.contains(&number);
+    let rest = words.next();
+    let set_off = rest.is_none() || separated || rest.is_some_and(|w| w == "—" || w == "-");
+    (!number.is_empty() && is_number && set_off).then_some(variant)
+}
+
+/// A word without the punctuation after it, and whether there was any
+fn word(word: &str) -> (&str, bool) {
+    let trimmed = word.trim_end_matches(['.', ':', ',', ';', '—']);
+    (trimmed, trimmed.len() != word.len())
+}
+
+/// Whether a line is a scene break like `* * *` or `-----`
+fn is_rule(line: &str) -> bool {
+    let marks = line.chars().filter(|c| !c.is_whitespace()).count();
+    marks >= 3
+        && line
+            .chars()
+            .all(|c| c.is_whitespace() || matches!(c, '*' | '-' | '_' | '=' | '~'))
+}
+
+/// Split plain text into sections at its chapter and part headings. Paragraphs are separated
+/// by blank lines, and the lines inside them are joined, since they were only wrapped to fit
+pub fn parse(text: &str) -> Vec<Node> {
+    let mut sections = vec![];
+    let mut current: Vec<Node> = vec![];
+    let mut headings = 0;
+    for block in blocks(text) {
+        let (first, rest) = block.split_first().expect("blocks aren't empty");
+        let Some(variant) = heading(first) else {
+            current.push(paragraph(&block));
+            continue;
+        };
+        // a chapter right after its part's heading goes in the same section
+        if current.iter().any(|n| !is_heading(n)) {
+            sections.push(section(sections.len(), std::mem::take(&mut current)));
+        }
+        let id = format!("heading-{}", headings);
+        headings += 1;
+        let name = if variant == ElementVariant::H1 {
+            "h1"
+        } else {
+            "h2"
+        };
+        let attributes = vec![("id".to_owned(), id)];
+        let text = Node::Text(collapse(first));
+        let heading = Element::build(name, variant, attributes, vec![text]);
+        current.push(Node::Element(heading));
+        if !rest.is_empty() {
+            current.push(paragraph(rest));
+        }
+    }
+    if !current.is_empty() {
+        sections.push(section(sections.len(), current));
+    }
+    sections
+}
+
+/// The title and authors given in a header like Project Gutenberg's, which has lines such as
+/// `Title: Frankenstein` before the text starts
+pub fn header(text: &str) -> (Option<String>, Vec<String>) {
+    let (mut title, mut authors) = (None, vec![]);
+    for line in text.lines().take(HEADER_LINES) {
+        if heading(line).is_some() {
+            break;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "Title" if title.is_none() && !value.is_empty() => title = Some(value.to_owned()),
+            "Author" if !value.is_empty() => authors.push(value.to_owned()),
+            _ => (),
+        }
+    }
+    (title, authors)
+}
+
+/// Runs of non-blank lines
+fn blocks(text: &str) -> Vec<Vec<&str>> {
+    let mut blocks = vec![];
+    let mut block = vec![];
+    for line in text.lines() {
+        if line.trim().is_empty() {
+            if !block.is_empty() {
+                blocks.push(std::mem::take(&mut block));
+            }
+        } else {
+            block.push(line);
+        }
+    }
+    if !block.is_empty() {
+        blocks.push(block);
+    }
+    blocks
+}
+
+fn paragraph(lines: &[&str]) -> Node {
+    if let [line] = lines {
+        if is_rule(line) {
+            return Node::Element(Element::build("hr", ElementVariant::Hr, vec![], vec![]));
+        }
+    }
+    let text = Node::Text(collapse(&lines.join(" ")));
+    Node::Element(Element::build("p", ElementVariant::P, vec![], vec![text]))
+}
+
+fn collapse(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::epub::{ElementVariant, Node};
+
+    use super::{header, heading, parse};
+
+    #[test]
+    fn headings() {
+        assert_eq!(heading("CHAPTER XII"), Some(ElementVariant::H2));
+        assert_eq!(heading("  Chapter 3. The Storm"), Some(ElementVariant::H2));
+        assert_eq!(heading("Letter 4: Arrival"), Some(ElementVariant::H2));
+        assert_eq!(heading("Part Two"), Some(ElementVariant::H1));
+        assert_eq!(heading("BOOK THE FIRST"), None);
+        assert_eq!(heading("VOLUME I."), Some(ElementVariant::H1));
+        assert_eq!(heading("PREFACE"), Some(ElementVariant::H2));
+        assert_eq!(heading("Chapter 1 — Beginnings"), Some(ElementVariant::H2));
+
+        assert_eq!(heading("Book one of the series was better."), None);
+        assert_eq!(heading("Chapter I said, was the worst."), None);
+        assert_eq!(heading("Introduction of the new rules took years."), None);
+        assert_eq!(heading("Chapter"), None);
+        assert_eq!(heading(""), None);
+    }
+
+    const TEXT: &str = "Title: A Short Tale\r\nAuthor: Anonymous\r\n\r\n\
+PART ONE\r\n\r\n\
+CHAPTER I.\r\n\r\n\
+It was a dark and stormy\r\nnight.\r\n\r\n\
+* * *\r\n\r\n\
+Morning came.\r\n\r\n\
+CHAPTER II. The Day\r\nAfter the night\r\ncame the day.\r\n\r\n\
+Part Two\r\n\r\n\
+The end.\r\n";
+
+    fn texts(node: &Node) -> Vec<&str> {
+        node.iter().filter_map(Node::text).collect()
+    }
+
+    #[test]
+    fn sections() {
+        let sections = parse(TEXT);
+        assert_eq!(sections.len(), 4);
+
+        assert_eq!(
+            texts(&sections[0]),
+            ["Title: A Short Tale Author: Anonymous"]
+        );
+        assert_eq!(
+            texts(&sections[1]),
+            [
+                "PART ONE",
+                "CHAPTER I.",
+                "It was a dark and stormy night.",
+                "Morning came."
+            ]
+        );
+        let variants: Vec<_> = sections[1]
+            .iter()
+            .filter_map(|n| Some(n.element()?.variant()))
+            .collect();
+        assert!(variants.contains(&ElementVariant::H1));
+        assert!(variants.contains(&ElementVariant::Hr));
+        assert_eq!(
+            texts(&sections[2]),
+            ["CHAPTER II. The Day", "After the night came the day."]
+        );
+        assert!(sections[3].find_by_id("heading-3").is_some());
+
+        assert_eq!(
+            header(TEXT),
+            (
+                Some("A Short Tale".to_owned()),
+                vec!["Anonymous".to_owned()]
+            )
+        );
+    }
+}
@@ -38,10 +38,6 @@ impl Default for TypesetConfig {
 //     }
 // }
 
-#[derive(Clone, Default, Debug)]
-#[allow(dead_code)]
-pub struct FontWeight(f32);
-
 // #[derive(Clone, Debug)]
 // pub enum ContentElement {
 //     Text(TextObject),
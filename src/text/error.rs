@@ -13,12 +13,12 @@ pub enum TextError {
     #[error("unable to parse ttf file")]
     TtfParse(#[from] ttf_parser::FaceParsingError),
 
-    #[error("")]
-    PageOverflow,
-
     #[error("content overflowed")]
     ContentOverflow(TypesetText, usize),
 
+    #[error("malformed woff font: {0}")]
+    Woff(&'static str),
+
     #[error("specified glyph {0} not found in font")]
     NoGlyph(char),
 }
@@ -2,7 +2,8 @@ mod face;
 mod family;
 mod index;
 mod style;
+mod woff;
 
 pub use family::Family;
-pub use index::FontIndexer;
+pub use index::{FontIndexer, IndexedFont};
 pub use style::FontStyle;
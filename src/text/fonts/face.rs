@@ -37,15 +37,13 @@ impl Face {
     pub fn space_width(&self, point_size: f32) -> Result<f32, TextError> {
         let scale = self.scale_factor(point_size);
         let face = self.as_ttf_face()?;
-        let gid = face
-            .glyph_index(' ')
-            .ok_or_else(|| TextError::NoGlyph(' '))?;
+        let gid = face.glyph_index(' ').ok_or(TextError::NoGlyph(' '))?;
         face.glyph_hor_advance(gid)
-            .ok_or_else(|| TextError::NoGlyph(' '))
+            .ok_or(TextError::NoGlyph(' '))
             .map(|adv| adv as f32 * scale)
     }
 
-    pub fn as_ttf_face(&self) -> Result<ttf_parser::Face, TextError> {
+    pub fn as_ttf_face(&self) -> Result<ttf_parser::Face<'_>, TextError> {
         ttf_parser::Face::parse(&self.bytes, 0).map_err(Into::into)
     }
 }
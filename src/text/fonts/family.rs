@@ -1,8 +1,6 @@
-use std::path::Path;
-
 use crate::text::TextError;
 
-use super::{face::Face, index::IndexedFont, style::FontStyle};
+use super::{face::Face, style::FontStyle};
 
 #[derive(Debug, Default, Clone)]
 pub struct Family {
@@ -10,25 +8,21 @@ pub struct Family {
     pub faces: Vec<Face>,
 }
 impl Family {
-    pub fn from_font<P>(p: P) -> Result<Self, TextError>
-    where
-        P: AsRef<Path>,
-    {
-        let i = IndexedFont::new(p)?;
-        let f = Face::new(&i)?;
-        Ok(Family {
-            name: i.family,
-            faces: vec![f],
-        })
-    }
-
     pub fn face(&self, style: FontStyle) -> Result<&Face, TextError> {
         self.faces
             .iter()
             .find(|s| s.style() == style)
             .ok_or(TextError::MissingFace)
     }
-    pub fn face_styles(&self) -> impl Iterator<Item = FontStyle> + use<'_> {
-        self.faces.iter().map(|f| f.style())
+    /// Borrow faces from another family for the styles this one lacks
+    pub fn with_fallback(mut self, other: &Family) -> Self {
+        let missing: Vec<_> = other
+            .faces
+            .iter()
+            .filter(|f| self.face(f.style()).is_err())
+            .cloned()
+            .collect();
+        self.faces.extend(missing);
+        self
     }
 }
@@ -10,9 +10,9 @@ use crate::text::TextError;
 use super::face::Face;
 use super::family::Family;
 use super::style::FontStyle;
+use super::woff;
 
 pub struct IndexedFont {
-    path: PathBuf,
     pub bytes: Vec<u8>,
     pub family: String,
     pub style: FontStyle,
@@ -23,9 +23,22 @@ impl IndexedFont {
         P: AsRef<Path>,
     {
         let bytes = std::fs::read(&entry)?;
-        let (family, subfamily) = read_font_metadata(&bytes);
+        Self::from_bytes(bytes, entry)
+    }
+
+    /// A font that has already been read, such as one embedded in a book. WOFF and WOFF2
+    /// fonts are unpacked
+    pub fn from_bytes<P>(bytes: Vec<u8>, path: P) -> Result<Self, TextError>
+    where
+        P: AsRef<Path>,
+    {
+        let bytes = woff::decode(bytes)?;
+        let (mut family, subfamily) = read_font_metadata(&bytes)?;
+        if family.is_empty() {
+            let stem = path.as_ref().file_stem().unwrap_or_default();
+            family = stem.to_string_lossy().into_owned();
+        }
         Ok(Self {
-            path: entry.as_ref().to_owned(),
             bytes,
             family,
             style: subfamily,
@@ -38,11 +51,17 @@ pub struct FontIndexer {
 }
 impl FontIndexer {
     pub fn new(path: &str) -> Self {
-        let mut fonts: HashMap<String, Vec<IndexedFont>> = HashMap::new();
-        let scanner = IndexScanner::scan(path);
-        scanner.for_each(|face| {
+        Self::from_fonts(IndexScanner::scan(path))
+    }
+
+    pub fn from_fonts<I>(fonts: I) -> Self
+    where
+        I: IntoIterator<Item = IndexedFont>,
+    {
+        let mut index: HashMap<String, Vec<IndexedFont>> = HashMap::new();
+        fonts.into_iter().for_each(|face| {
             let fc = face.family.clone();
-            match fonts.entry(fc.clone()) {
+            match index.entry(fc.clone()) {
                 Entry::Occupied(mut e) => {
                     e.get_mut().push(face);
                 }
@@ -51,7 +70,18 @@ impl FontIndexer {
                 }
             }
         });
-        Self { fonts }
+        Self { fonts: index }
+    }
+
+    /// The family with a regular face and the most styles, as the one most likely meant
+    /// for body text
+    pub fn main_family(&self) -> Option<Family> {
+        let (name, _) = self
+            .fonts
+            .iter()
+            .filter(|(_, faces)| faces.iter().any(|f| f.style == FontStyle::Regular))
+            .max_by_key(|(name, faces)| (faces.len(), std::cmp::Reverse(*name)))?;
+        self.get_family(name)
     }
 
     pub fn get_family(&self, family: &str) -> Option<Family> {
@@ -63,9 +93,6 @@ impl FontIndexer {
             }
         })
     }
-    pub fn families(&self) -> impl Iterator<Item = &str> {
-        std::iter::empty()
-    }
 }
 struct IndexScanner {
     dirs: Vec<PathBuf>,
@@ -94,7 +121,10 @@ impl Iterator for IndexScanner {
                             self.dirs.push(ep);
                             continue;
                         }
-                        return IndexedFont::new(entry.path()).ok();
+                        match IndexedFont::new(&ep) {
+                            Ok(font) => return Some(font),
+                            Err(e) => tracing::warn!("unable to load font {:?}: {}", ep, e),
+                        }
                     }
                     None => {
                         self.files = None;
@@ -103,7 +133,7 @@ impl Iterator for IndexScanner {
                     _ => (),
                 }
             }
-            while let Some(dir) = self.dirs.pop() {
+            if let Some(dir) = self.dirs.pop() {
                 if let Ok(files) = fs::read_dir(&dir) {
                     self.files = Some(files);
                 }
@@ -112,10 +142,10 @@ impl Iterator for IndexScanner {
     }
 }
 
-fn read_font_metadata(data: &[u8]) -> (String, FontStyle) {
+fn read_font_metadata(data: &[u8]) -> Result<(String, FontStyle), TextError> {
     let mut family = String::new();
     let mut subfamily = FontStyle::Regular;
-    let face = ttf_parser::Face::parse(data, 0).unwrap();
+    let face = ttf_parser::Face::parse(data, 0)?;
     for name in face.names() {
         if name.platform_id == PlatformId::Windows {
             match name.name_id {
@@ -130,7 +160,7 @@ fn read_font_metadata(data: &[u8]) -> (String, FontStyle) {
             }
         }
     }
-    (family, subfamily)
+    Ok((family, subfamily))
 }
 
 fn read_utf16_string(raw: &[u8]) -> String {
@@ -141,5 +171,5 @@ fn read_utf16_string(raw: &[u8]) -> String {
             u16::from_be_bytes(a)
         })
         .collect();
-    String::from_utf16(&v).unwrap()
+    String::from_utf16_lossy(&v)
 }
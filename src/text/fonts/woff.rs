@@ -0,0 +1,683 @@
+use std::io::Read;
+
+use crate::text::TextError;
+
+const WOFF_SIGNATURE: &[u8] = b"wOFF";
+const WOFF2_SIGNATURE: &[u8] = b"wOF2";
+
+/// Tags a WOFF2 table directory can refer to by index
+const KNOWN_TAGS: [&[u8; 4]; 63] = [
+    b"cmap", b"head", b"hhea", b"hmtx", b"maxp", b"name", b"OS/2", b"post", b"cvt ", b"fpgm",
+    b"glyf", b"loca", b"prep", b"CFF ", b"VORG", b"EBDT", b"EBLC", b"gasp", b"hdmx", b"kern",
+    b"LTSH", b"PCLT", b"VDMX", b"vhea", b"vmtx", b"BASE", b"GDEF", b"GPOS", b"GSUB", b"EBSC",
+    b"JSTF", b"MATH", b"CBDT", b"CBLC", b"COLR", b"CPAL", b"SVG ", b"sbix", b"acnt", b"avar",
+    b"bdat", b"bloc", b"bsln", b"cvar", b"fdsc", b"feat", b"fmtx", b"fvar", b"gvar", b"hsty",
+    b"just", b"lcar", b"mort", b"morx", b"opbd", b"prop", b"trak", b"Zapf", b"Silf", b"Glat",
+    b"Gloc", b"Feat", b"Sill",
+];
+
+/// Turn WOFF and WOFF2 fonts into the plain sfnt data `ttf_parser` reads. Anything else is
+/// returned untouched
+pub fn decode(bytes: Vec<u8>) -> Result<Vec<u8>, TextError> {
+    match bytes.get(..4) {
+        Some(WOFF_SIGNATURE) => decode_woff(&bytes),
+        Some(WOFF2_SIGNATURE) => decode_woff2(&bytes),
+        _ => Ok(bytes),
+    }
+}
+
+fn malformed(reason: &'static str) -> TextError {
+    TextError::Woff(reason)
+}
+
+/// Big-endian reader over a slice
+struct Stream<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+impl<'a> Stream<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], TextError> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .ok_or(malformed("length overflow"))?;
+        let bytes = self
+            .data
+            .get(self.pos..end)
+            .ok_or(malformed("truncated data"))?;
+        self.pos = end;
+        Ok(bytes)
+    }
+    fn u8(&mut self) -> Result<u8, TextError> {
+        Ok(self.bytes(1)?[0])
+    }
+    fn u16(&mut self) -> Result<u16, TextError> {
+        let b = self.bytes(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+    fn i16(&mut self) -> Result<i16, TextError> {
+        Ok(self.u16()? as i16)
+    }
+    fn u32(&mut self) -> Result<u32, TextError> {
+        let b = self.bytes(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+    /// Variable length encoding of 32 bit values used by WOFF2 table directories
+    fn base128(&mut self) -> Result<u32, TextError> {
+        let mut value: u32 = 0;
+        for i in 0..5 {
+            let b = self.u8()?;
+            if i == 0 && b == 0x80 {
+                return Err(malformed("leading zeros in UIntBase128"));
+            }
+            if value & 0xfe00_0000 != 0 {
+                return Err(malformed("UIntBase128 overflow"));
+            }
+            value = (value << 7) | (b & 0x7f) as u32;
+            if b & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(malformed("UIntBase128 too long"))
+    }
+    /// Variable length encoding of 16 bit values used by transformed glyphs
+    fn u255_16(&mut self) -> Result<u16, TextError> {
+        Ok(match self.u8()? {
+            253 => self.u16()?,
+            254 => self.u8()? as u16 + 506,
+            255 => self.u8()? as u16 + 253,
+            code => code as u16,
+        })
+    }
+}
+
+fn decode_woff(bytes: &[u8]) -> Result<Vec<u8>, TextError> {
+    let mut header = Stream::new(bytes);
+    header.bytes(4)?;
+    let flavor = header.u32()?;
+    header.u32()?;
+    let num_tables = header.u16()?;
+    if num_tables == 0 {
+        return Err(malformed("no tables"));
+    }
+    header.u16()?;
+    let mut sfnt_size = SfntSize::new(header.u32()?);
+    header.bytes(24)?;
+
+    let mut tables = vec![];
+    for _ in 0..num_tables {
+        let tag: [u8; 4] = header.bytes(4)?.try_into().unwrap();
+        let offset = header.u32()? as usize;
+        let comp_length = header.u32()? as usize;
+        let orig_length = header.u32()? as usize;
+        header.u32()?;
+        sfnt_size.take(orig_length)?;
+
+        let data = Stream::new(bytes).bytes(offset + comp_length)?;
+        let data = &data[offset..];
+        let data = if comp_length < orig_length {
+            let mut table = Vec::with_capacity(orig_length);
+            flate2::read::ZlibDecoder::new(data)
+                .take(orig_length as u64 + 1)
+                .read_to_end(&mut table)?;
+            table
+        } else {
+            data.to_vec()
+        };
+        if data.len() != orig_length {
+            return Err(malformed("table length mismatch"));
+        }
+        tables.push((tag, data));
+    }
+    build_sfnt(flavor, tables)
+}
+
+/// What's left of the size a font declares its sfnt data to be, which the lengths of its
+/// tables are checked against before anything is allocated for them
+struct SfntSize {
+    remaining: usize,
+}
+impl SfntSize {
+    fn new(total: u32) -> Self {
+        Self {
+            remaining: total as usize,
+        }
+    }
+    fn take(&mut self, len: usize) -> Result<(), TextError> {
+        self.remaining = self
+            .remaining
+            .checked_sub(len)
+            .ok_or(malformed("tables larger than the font"))?;
+        Ok(())
+    }
+}
+
+struct Woff2Table {
+    tag: [u8; 4],
+    orig_length: usize,
+    // length in the decompressed stream, which differs for transformed tables
+    length: usize,
+    transformed: bool,
+}
+
+fn decode_woff2(bytes: &[u8]) -> Result<Vec<u8>, TextError> {
+    let mut header = Stream::new(bytes);
+    header.bytes(4)?;
+    let flavor = header.u32()?;
+    if &flavor.to_be_bytes() == b"ttcf" {
+        return Err(malformed("font collections aren't supported"));
+    }
+    header.u32()?;
+    let num_tables = header.u16()?;
+    if num_tables == 0 {
+        return Err(malformed("no tables"));
+    }
+    header.u16()?;
+    let total_sfnt_size = header.u32()?;
+    let compressed_size = header.u32()? as usize;
+    header.bytes(24)?;
+
+    let mut sfnt_size = SfntSize::new(total_sfnt_size);
+    // transformed tables are smaller than the originals, so the stream fits in the font too
+    let mut stream_size = SfntSize::new(total_sfnt_size);
+    let mut directory = vec![];
+    for _ in 0..num_tables {
+        let flags = header.u8()?;
+        let tag = match flags & 0x3f {
+            63 => header.bytes(4)?.try_into().unwrap(),
+            i => *KNOWN_TAGS[i as usize],
+        };
+        let version = flags >> 6;
+        let orig_length = header.base128()? as usize;
+        // glyf and loca are transformed unless they say otherwise, other tables the reverse
+        let transformed = match &tag {
+            b"glyf" | b"loca" => version != 3,
+            _ => version != 0,
+        };
+        let length = match transformed {
+            true => header.base128()? as usize,
+            false => orig_length,
+        };
+        sfnt_size.take(orig_length)?;
+        stream_size.take(length)?;
+        directory.push(Woff2Table {
+            tag,
+            orig_length,
+            length,
+            transformed,
+        });
+    }
+
+    let compressed = header.bytes(compressed_size)?;
+    let length: usize = directory.iter().map(|t| t.length).sum();
+    let mut data = vec![];
+    brotli_decompressor::Decompressor::new(compressed, 4096)
+        .take(length as u64 + 1)
+        .read_to_end(&mut data)?;
+    if data.len() > length {
+        return Err(malformed("table data longer than its tables"));
+    }
+
+    let mut stream = Stream::new(&data);
+    let mut raw = vec![];
+    for table in directory.iter() {
+        raw.push(stream.bytes(table.length)?);
+    }
+    let table_data = |tag: &[u8; 4]| {
+        directory
+            .iter()
+            .position(|t| &t.tag == tag)
+            .map(|i| (&directory[i], raw[i]))
+    };
+
+    let mut tables = vec![];
+    let mut x_mins = vec![];
+    if let Some((glyf, glyf_data)) = table_data(b"glyf").filter(|(t, _)| t.transformed) {
+        let glyphs = reconstruct_glyf(glyf_data)?;
+        if glyphs.glyf.len() != glyf.orig_length {
+            tracing::debug!("reconstructed glyf size differs from the original");
+        }
+        tables.push((*b"glyf", glyphs.glyf));
+        tables.push((*b"loca", glyphs.loca));
+        x_mins = glyphs.x_mins;
+    }
+    for (table, data) in directory.iter().zip(raw.iter().copied()) {
+        match (&table.tag, table.transformed) {
+            (b"glyf" | b"loca", true) => (),
+            (b"hmtx", true) => {
+                let hhea = table_data(b"hhea").ok_or(malformed("missing hhea"))?.1;
+                let num_h_metrics = Stream::new(hhea.get(34..).unwrap_or_default()).u16()?;
+                let hmtx = reconstruct_hmtx(data, num_h_metrics as usize, &x_mins)?;
+                tables.push((table.tag, hmtx));
+            }
+            (b"head", false) if !x_mins.is_empty() => {
+                // point indexToLocFormat at the long loca we built
+                let mut head = data.to_vec();
+                let format = head.get_mut(50..52).ok_or(malformed("short head table"))?;
+                format.copy_from_slice(&1i16.to_be_bytes());
+                tables.push((table.tag, head));
+            }
+            (_, true) => return Err(malformed("unknown table transform")),
+            (_, false) => tables.push((table.tag, data.to_vec())),
+        }
+    }
+    build_sfnt(flavor, tables)
+}
+
+struct Glyphs {
+    glyf: Vec<u8>,
+    loca: Vec<u8>,
+    x_mins: Vec<i16>,
+}
+
+/// Rebuild the glyf and loca tables from the WOFF2 transformed glyf table. The rebuilt glyphs
+/// needn't be as compact as the originals, so loca always uses the long format. Also returns
+/// the xMin of every glyph, which transformed hmtx tables leave out
+fn reconstruct_glyf(data: &[u8]) -> Result<Glyphs, TextError> {
+    let mut header = Stream::new(data);
+    header.u16()?;
+    header.u16()?;
+    let num_glyphs = header.u16()? as usize;
+    header.u16()?;
+    let mut sizes = [0; 7];
+    for size in sizes.iter_mut() {
+        *size = header.u32()? as usize;
+    }
+    let mut n_contours = Stream::new(header.bytes(sizes[0])?);
+    let mut n_points = Stream::new(header.bytes(sizes[1])?);
+    let mut flags = Stream::new(header.bytes(sizes[2])?);
+    let mut glyphs = Stream::new(header.bytes(sizes[3])?);
+    let mut composites = Stream::new(header.bytes(sizes[4])?);
+    let mut bboxes = Stream::new(header.bytes(sizes[5])?);
+    let mut instructions = Stream::new(header.bytes(sizes[6])?);
+
+    let bitmap = bboxes.bytes(num_glyphs.div_ceil(32) * 4)?;
+    let has_bbox = |i: usize| bitmap[i >> 3] & (0x80 >> (i & 7)) != 0;
+
+    let mut glyf = vec![];
+    let mut offsets = vec![0];
+    let mut x_mins = vec![];
+    for i in 0..num_glyphs {
+        let contours = n_contours.i16()?;
+        let start = glyf.len();
+        let mut x_min = 0;
+        match contours {
+            0 => (),
+            -1 => {
+                if !has_bbox(i) {
+                    return Err(malformed("composite glyph without bbox"));
+                }
+                let bbox = bboxes.bytes(8)?;
+                x_min = Stream::new(bbox).i16()?;
+                glyf.extend_from_slice(&contours.to_be_bytes());
+                glyf.extend_from_slice(bbox);
+                let has_instructions = copy_components(&mut composites, &mut glyf)?;
+                if has_instructions {
+                    let len = glyphs.u255_16()?;
+                    glyf.extend_from_slice(&len.to_be_bytes());
+                    glyf.extend_from_slice(instructions.bytes(len as usize)?);
+                }
+            }
+            n if n > 0 => {
+                let mut end_points = vec![];
+                let mut total = 0u16;
+                for _ in 0..n {
+                    let points = n_points.u255_16()?;
+                    total = total
+                        .checked_add(points)
+                        .ok_or(malformed("too many points"))?;
+                    end_points.push(total.wrapping_sub(1));
+                }
+                let points = decode_triplets(&mut flags, &mut glyphs, total as usize)?;
+                let bbox = match has_bbox(i) {
+                    true => {
+                        let mut b = Stream::new(bboxes.bytes(8)?);
+                        [b.i16()?, b.i16()?, b.i16()?, b.i16()?]
+                    }
+                    false => points.iter().fold(
+                        [i16::MAX, i16::MAX, i16::MIN, i16::MIN],
+                        |[x0, y0, x1, y1], p| [x0.min(p.x), y0.min(p.y), x1.max(p.x), y1.max(p.y)],
+                    ),
+                };
+                x_min = bbox[0];
+
+                glyf.extend_from_slice(&contours.to_be_bytes());
+                bbox.iter()
+                    .for_each(|v| glyf.extend_from_slice(&v.to_be_bytes()));
+                end_points
+                    .iter()
+                    .for_each(|e| glyf.extend_from_slice(&e.to_be_bytes()));
+                let len = glyphs.u255_16()?;
+                glyf.extend_from_slice(&len.to_be_bytes());
+                glyf.extend_from_slice(instructions.bytes(len as usize)?);
+                encode_points(&points, &mut glyf);
+            }
+            _ => return Err(malformed("invalid contour count")),
+        }
+        if glyf.len() > start {
+            glyf.resize(glyf.len().next_multiple_of(4), 0);
+        }
+        offsets.push(glyf.len());
+        x_mins.push(x_min);
+    }
+
+    let loca = offsets
+        .iter()
+        .flat_map(|o| (*o as u32).to_be_bytes())
+        .collect();
+    Ok(Glyphs { glyf, loca, x_mins })
+}
+
+/// Copy a composite glyph's components, returning whether it has instructions
+fn copy_components(stream: &mut Stream, glyf: &mut Vec<u8>) -> Result<bool, TextError> {
+    const ARGS_ARE_WORDS: u16 = 0x0001;
+    const HAVE_SCALE: u16 = 0x0008;
+    const MORE_COMPONENTS: u16 = 0x0020;
+    const HAVE_XY_SCALE: u16 = 0x0040;
+    const HAVE_TWO_BY_TWO: u16 = 0x0080;
+    const HAVE_INSTRUCTIONS: u16 = 0x0100;
+
+    let mut has_instructions = false;
+    loop {
+        let flags = stream.u16()?;
+        has_instructions |= flags & HAVE_INSTRUCTIONS != 0;
+        let mut len = 2 + if flags & ARGS_ARE_WORDS != 0 { 4 } else { 2 };
+        if flags & HAVE_SCALE != 0 {
+            len += 2;
+        } else if flags & HAVE_XY_SCALE != 0 {
+            len += 4;
+        } else if flags & HAVE_TWO_BY_TWO != 0 {
+            len += 8;
+        }
+        glyf.extend_from_slice(&flags.to_be_bytes());
+        glyf.extend_from_slice(stream.bytes(len)?);
+        if flags & MORE_COMPONENTS == 0 {
+            return Ok(has_instructions);
+        }
+    }
+}
+
+struct GlyphPoint {
+    x: i16,
+    y: i16,
+    on_curve: bool,
+}
+
+/// Read a simple glyph's points from their flags and triplet encoded coordinates
+fn decode_triplets(
+    flags: &mut Stream,
+    glyphs: &mut Stream,
+    count: usize,
+) -> Result<Vec<GlyphPoint>, TextError> {
+    let with_sign = |flag: u8, value: i32| if flag & 1 != 0 { value } else { -value };
+    let (mut x, mut y) = (0i32, 0i32);
+    let mut points = Vec::with_capacity(count);
+    for _ in 0..count {
+        let flag = flags.u8()?;
+        let on_curve = flag & 0x80 == 0;
+        let flag = flag & 0x7f;
+        let data_len = match flag {
+            0..84 => 1,
+            84..120 => 2,
+            120..124 => 3,
+            _ => 4,
+        };
+        let b: Vec<i32> = glyphs.bytes(data_len)?.iter().map(|b| *b as i32).collect();
+        let f = flag as i32;
+        let (dx, dy) = match flag {
+            0..10 => (0, with_sign(flag, ((f & 14) << 7) + b[0])),
+            10..20 => (with_sign(flag, (((f - 10) & 14) << 7) + b[0]), 0),
+            20..84 => {
+                let b0 = f - 20;
+                (
+                    with_sign(flag, 1 + (b0 & 0x30) + (b[0] >> 4)),
+                    with_sign(flag >> 1, 1 + ((b0 & 0x0c) << 2) + (b[0] & 0x0f)),
+                )
+            }
+            84..120 => {
+                let b0 = f - 84;
+                (
+                    with_sign(flag, 1 + ((b0 / 12) << 8) + b[0]),
+                    with_sign(flag >> 1, 1 + (((b0 % 12) >> 2) << 8) + b[1]),
+                )
+            }
+            120..124 => (
+                with_sign(flag, (b[0] << 4) + (b[1] >> 4)),
+                with_sign(flag >> 1, ((b[1] & 0x0f) << 8) + b[2]),
+            ),
+            _ => (
+                with_sign(flag, (b[0] << 8) + b[1]),
+                with_sign(flag >> 1, (b[2] << 8) + b[3]),
+            ),
+        };
+        x += dx;
+        y += dy;
+        points.push(GlyphPoint {
+            x: x as i16,
+            y: y as i16,
+            on_curve,
+        });
+    }
+    Ok(points)
+}
+
+/// Write points in the plain glyf encoding: flags, then x and y deltas
+fn encode_points(points: &[GlyphPoint], glyf: &mut Vec<u8>) {
+    const ON_CURVE: u8 = 0x01;
+    const X_SHORT: u8 = 0x02;
+    const Y_SHORT: u8 = 0x04;
+    const X_SAME_OR_POSITIVE: u8 = 0x10;
+    const Y_SAME_OR_POSITIVE: u8 = 0x20;
+
+    let mut flags = vec![];
+    let mut xs = vec![];
+    let mut ys = vec![];
+    let (mut last_x, mut last_y) = (0i16, 0i16);
+    for p in points {
+        let mut flag = if p.on_curve { ON_CURVE } else { 0 };
+        let dx = p.x.wrapping_sub(last_x);
+        let dy = p.y.wrapping_sub(last_y);
+        match dx {
+            0 => flag |= X_SAME_OR_POSITIVE,
+            -255..=255 => {
+                flag |= X_SHORT | if dx > 0 { X_SAME_OR_POSITIVE } else { 0 };
+                xs.push(dx.unsigned_abs() as u8);
+            }
+            _ => xs.extend_from_slice(&dx.to_be_bytes()),
+        }
+        match dy {
+            0 => flag |= Y_SAME_OR_POSITIVE,
+            -255..=255 => {
+                flag |= Y_SHORT | if dy > 0 { Y_SAME_OR_POSITIVE } else { 0 };
+                ys.push(dy.unsigned_abs() as u8);
+            }
+            _ => ys.extend_from_slice(&dy.to_be_bytes()),
+        }
+        flags.push(flag);
+        last_x = p.x;
+        last_y = p.y;
+    }
+    glyf.extend(flags);
+    glyf.extend(xs);
+    glyf.extend(ys);
+}
+
+/// Rebuild an hmtx table whose left side bearings were left out in favour of glyph xMins
+fn reconstruct_hmtx(
+    data: &[u8],
+    num_h_metrics: usize,
+    x_mins: &[i16],
+) -> Result<Vec<u8>, TextError> {
+    let mut stream = Stream::new(data);
+    let flags = stream.u8()?;
+    let mut advances = vec![];
+    for _ in 0..num_h_metrics {
+        advances.push(stream.u16()?);
+    }
+    let mut bearing = |i: usize, omitted: bool| match omitted {
+        true => Ok(x_mins.get(i).copied().unwrap_or_default()),
+        false => stream.i16(),
+    };
+
+    let mut hmtx = vec![];
+    for (i, advance) in advances.iter().enumerate() {
+        hmtx.extend_from_slice(&advance.to_be_bytes());
+        hmtx.extend_from_slice(&bearing(i, flags & 1 != 0)?.to_be_bytes());
+    }
+    for i in num_h_metrics..x_mins.len() {
+        hmtx.extend_from_slice(&bearing(i, flags & 2 != 0)?.to_be_bytes());
+    }
+    Ok(hmtx)
+}
+
+/// Lay tables out in an sfnt file with its table directory
+fn build_sfnt(flavor: u32, mut tables: Vec<([u8; 4], Vec<u8>)>) -> Result<Vec<u8>, TextError> {
+    tables.sort_by_key(|(tag, _)| *tag);
+    let num_tables = tables.len() as u32;
+    let entry_selector = num_tables.checked_ilog2().unwrap_or(0);
+    let search_range = (1 << entry_selector) * 16;
+    let range_shift = (num_tables * 16).saturating_sub(search_range);
+
+    let mut sfnt = vec![];
+    sfnt.extend_from_slice(&flavor.to_be_bytes());
+    for v in [num_tables, search_range, entry_selector, range_shift] {
+        let v = u16::try_from(v).map_err(|_| malformed("too many tables"))?;
+        sfnt.extend_from_slice(&v.to_be_bytes());
+    }
+
+    let mut offset = 12 + 16 * tables.len();
+    for (tag, data) in tables.iter() {
+        sfnt.extend_from_slice(tag);
+        sfnt.extend_from_slice(&checksum(data).to_be_bytes());
+        sfnt.extend_from_slice(&(offset as u32).to_be_bytes());
+        sfnt.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        offset += data.len().next_multiple_of(4);
+    }
+    for (_, data) in tables {
+        sfnt.extend_from_slice(&data);
+        sfnt.resize(sfnt.len().next_multiple_of(4), 0);
+    }
+    Ok(sfnt)
+}
+
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::{build_sfnt, decode};
+
+    fn tables(sfnt: &[u8]) -> Vec<([u8; 4], Vec<u8>)> {
+        let face = ttf_parser::RawFace::parse(sfnt, 0).unwrap();
+        face.table_records
+            .into_iter()
+            .map(|r| {
+                let range = r.offset as usize..(r.offset + r.length) as usize;
+                (r.tag.to_bytes(), sfnt[range].to_vec())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn woff() {
+        let ttf = std::fs::read("testfiles/fonts/DejaVuSansMono.ttf").unwrap();
+        let tables = tables(&ttf);
+
+        // compress every table that gets smaller, like sfnt2woff does
+        let header_len = 44 + 20 * tables.len();
+        let mut directory = vec![];
+        let mut data = vec![];
+        for (tag, table) in tables.iter() {
+            let mut encoder = flate2::write::ZlibEncoder::new(vec![], Default::default());
+            encoder.write_all(table).unwrap();
+            let compressed = encoder.finish().unwrap();
+            let stored = if compressed.len() < table.len() {
+                compressed
+            } else {
+                table.clone()
+            };
+            directory.extend_from_slice(tag);
+            for v in [header_len + data.len(), stored.len(), table.len(), 0] {
+                directory.extend_from_slice(&(v as u32).to_be_bytes());
+            }
+            data.extend_from_slice(&stored);
+            data.resize(data.len().next_multiple_of(4), 0);
+        }
+        let mut woff = b"wOFF".to_vec();
+        woff.extend_from_slice(&ttf[..4]);
+        woff.extend_from_slice(&((header_len + data.len()) as u32).to_be_bytes());
+        woff.extend_from_slice(&(tables.len() as u16).to_be_bytes());
+        woff.extend_from_slice(&[0, 0]);
+        woff.extend_from_slice(&(ttf.len() as u32).to_be_bytes());
+        woff.resize(44, 0);
+        woff.extend(directory);
+        woff.extend(data);
+
+        // tables claiming more than the font's declared size aren't unpacked
+        let mut small = woff.clone();
+        small[16..20].copy_from_slice(&1024u32.to_be_bytes());
+        assert!(decode(small).is_err());
+
+        let decoded = decode(woff).unwrap();
+        assert_eq!(
+            decoded,
+            build_sfnt(u32::from_be_bytes([0, 1, 0, 0]), tables).unwrap()
+        );
+        let face = ttf_parser::Face::parse(&decoded, 0).unwrap();
+        assert!(face.glyph_index('a').is_some());
+    }
+
+    #[test]
+    fn woff_without_tables() {
+        let mut woff = b"wOFF".to_vec();
+        woff.extend_from_slice(&[0, 1, 0, 0]);
+        woff.extend_from_slice(&44u32.to_be_bytes());
+        woff.extend_from_slice(&0u16.to_be_bytes());
+        woff.resize(44, 0);
+        assert!(decode(woff).is_err());
+    }
+
+    #[test]
+    fn woff2() {
+        let woff2 = std::fs::read("testfiles/fonts/woff/OpenSans-Regular.woff2").unwrap();
+        let decoded = decode(woff2).unwrap();
+        let face = ttf_parser::Face::parse(&decoded, 0).unwrap();
+
+        struct Outline;
+        impl ttf_parser::OutlineBuilder for Outline {
+            fn move_to(&mut self, _: f32, _: f32) {}
+            fn line_to(&mut self, _: f32, _: f32) {}
+            fn quad_to(&mut self, _: f32, _: f32, _: f32, _: f32) {}
+            fn curve_to(&mut self, _: f32, _: f32, _: f32, _: f32, _: f32, _: f32) {}
+            fn close(&mut self) {}
+        }
+
+        // a simple glyph and a composite one
+        let mut bboxes = vec![];
+        for c in ['a', 'é'] {
+            let id = face.glyph_index(c).unwrap();
+            let bbox = face.outline_glyph(id, &mut Outline).unwrap();
+            assert!(face.glyph_hor_advance(id).unwrap() > 0);
+            assert_eq!(face.glyph_hor_side_bearing(id), Some(bbox.x_min));
+            bboxes.push(bbox);
+        }
+        // the accent sits above the letter it's composed with
+        assert!(bboxes[1].y_max > bboxes[0].y_max);
+    }
+
+    #[test]
+    fn passthrough() {
+        let ttf = std::fs::read("testfiles/fonts/DejaVuSansMono.ttf").unwrap();
+        assert_eq!(decode(ttf.clone()).unwrap(), ttf);
+    }
+}
@@ -51,3 +51,15 @@ pub struct Rect {
     pub width: usize,
     pub height: usize,
 }
+
+/// An area on the page, between its top-left and bottom-right corners
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct Bounds {
+    pub min: Point,
+    pub max: Point,
+}
+impl Bounds {
+    pub fn contains(&self, point: Point) -> bool {
+        (self.min.x..self.max.x).contains(&point.x) && (self.min.y..self.max.y).contains(&point.y)
+    }
+}
@@ -1,5 +1,3 @@
-use ttf_parser::{Face, GlyphId};
-
 use super::caret::Caret;
 use super::fonts::FontStyle;
 use super::{Glyph, TextError, TypesetConfig};
@@ -80,7 +78,6 @@ where
         let pos = caret.point();
         caret.advance(hadv);
 
-        // kern(&face, last, gid);
         // last = Some(gid);
 
         word_buffer.push(Glyph {
@@ -98,24 +95,3 @@ where
 
     Ok(t)
 }
-
-fn kern(face: &Face, left: Option<GlyphId>, right: GlyphId) -> f32 {
-    let mut h_kern: Vec<_> = face
-        .tables()
-        .kern
-        .iter()
-        .flat_map(|c| c.subtables)
-        .filter(|st| st.horizontal && !st.variable)
-        .collect();
-    if let Some(last_gid) = left {
-        let kern_val = h_kern
-            .iter_mut()
-            .find_map(|i| i.glyphs_kerning(last_gid, right))
-            .map(f32::from)
-            .unwrap_or_default();
-        if kern_val != 0.0 {
-            dbg!(last_gid, right, kern_val);
-        }
-    }
-    0.0
-}